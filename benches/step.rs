@@ -0,0 +1,115 @@
+//! Speed of the steps of the game, and of drawing its frames.
+//!
+//! ```text
+//! cargo bench
+//! ```
+
+use app::input::Keymap;
+use app::render::{self, Prompts, Scene, Theme};
+use app::GameState;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use snake_game::{Size, SnakeGameLogic};
+use std::hint::black_box;
+use std::time::Duration;
+
+/// The front-end of the game, which draws the frames.
+/// Most of it is not used here, and its tests are not built.
+#[allow(dead_code, unused_imports)]
+#[path = "../src/main.rs"]
+mod app;
+
+/// A game the snake never leaves, going through the walls.
+fn new_game() -> SnakeGameLogic {
+    let mut logic = SnakeGameLogic::new(Size(20, 20), 1, 0);
+    logic.set_wrap(true);
+    logic
+}
+
+fn step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("step");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("20x20", |b| {
+        let mut logic = new_game();
+        b.iter(|| {
+            // The snake only goes straight, so it crashes into itself once its row is full.
+            if black_box(logic.step()).is_over() {
+                logic = new_game();
+            }
+        })
+    });
+    group.finish();
+}
+
+/// The feed moves on every step, on a field full of obstacles but the row of the snake.
+fn place_feed(c: &mut Criterion) {
+    c.bench_function("place feed on nearly full field", |b| {
+        b.iter_batched(
+            || {
+                let mut logic = SnakeGameLogic::new(Size(40, 40), 1, 0);
+                logic.set_wrap(true);
+                logic.place_obstacles(usize::MAX);
+                logic.set_feed_ttl(Some(1));
+                logic
+            },
+            |mut logic| black_box(logic.step()),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn scene<'a>(logic: &'a SnakeGameLogic, prompts: &'a Prompts) -> Scene<'a> {
+    Scene {
+        logic,
+        state: GameState::Playing,
+        high_score: 0,
+        elapsed: Duration::ZERO,
+        time_left: None,
+        level: None,
+        speed: 100,
+        difficulty: None,
+        daily: None,
+        help: None,
+        prompts,
+        quitting: false,
+        leaderboard: None,
+        name: None,
+        panel: None,
+        flash: false,
+        gradient: true,
+        ghost: None,
+        trail: &[],
+    }
+}
+
+/// Filling the screen of a frame after a step, in the buffer of the last frame or in a new one.
+fn draw(c: &mut Criterion) {
+    let prompts = Keymap::default().prompts();
+    let theme = Theme::default();
+    let mut group = c.benchmark_group("draw frame");
+    group.bench_function("reused screen", |b| {
+        let mut logic = new_game();
+        let mut screen = Vec::new();
+        b.iter(|| {
+            if logic.step().is_over() {
+                logic = new_game();
+            }
+            render::fill_screen(&scene(&logic, &prompts), &theme, &mut screen);
+            black_box(&screen);
+        })
+    });
+    group.bench_function("new screen", |b| {
+        let mut logic = new_game();
+        b.iter(|| {
+            if logic.step().is_over() {
+                logic = new_game();
+            }
+            let mut screen = Vec::new();
+            render::fill_screen(&scene(&logic, &prompts), &theme, &mut screen);
+            black_box(screen)
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, step, place_feed, draw);
+criterion_main!(benches);
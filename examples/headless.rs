@@ -0,0 +1,43 @@
+//! Play a game without a terminal and print the final score.
+//!
+//! ```text
+//! cargo run --example headless -- [STEPS] [SEED]
+//! ```
+
+use snake_game::{Direction, Size, SnakeGameLogic};
+use std::env;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let steps: usize = args
+        .next()
+        .map_or(1000, |s| s.parse().expect("invalid STEPS"));
+    let seed: u64 = args.next().map_or(0, |s| s.parse().expect("invalid SEED"));
+
+    let mut logic = SnakeGameLogic::new(Size(20, 20), 1, seed);
+    let mut n = 0;
+    while n < steps {
+        // Head straight for the feed. It does not avoid its own body.
+        let head = *logic.body().next().unwrap();
+        if let Some(feed) = logic.feeds().first() {
+            let dir = if feed.0 < head.0 {
+                Direction::Left
+            } else if feed.0 > head.0 {
+                Direction::Right
+            } else if feed.1 < head.1 {
+                Direction::Up
+            } else {
+                Direction::Down
+            };
+            logic.set_dir(dir);
+        }
+
+        n += 1;
+        let result = logic.step();
+        if result.is_over() {
+            println!("{:?} after {} steps", result, n);
+            break;
+        }
+    }
+    println!("score: {}", logic.score());
+}
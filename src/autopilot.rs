@@ -0,0 +1,106 @@
+//! A player which steers the snake by itself.
+
+use crate::{Coord, Direction, SnakeGameLogic};
+use std::collections::{HashMap, VecDeque};
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Down,
+    Direction::Left,
+    Direction::Right,
+];
+
+/// Choose the next direction of the snake.
+///
+/// It takes the shortest path to the nearest food, avoiding walls, obstacles, bombs, portals, poison, reverse feeds and the body.
+/// If no food can be reached, it makes any move which does not end the game right away.
+pub fn autopilot(logic: &SnakeGameLogic) -> Direction {
+    let head = *logic.body().next().unwrap();
+    let dir = *logic.get_dir();
+
+    // Breadth-first search from the head, remembering the first move to each cell.
+    let mut first_moves: HashMap<Coord, Direction> = HashMap::new();
+    let mut queue = VecDeque::new();
+    for d in DIRECTIONS.into_iter().filter(|d| *d != dir.opposite()) {
+        if let Some(c) = safe_cell(logic, &head, &d) {
+            if first_moves.insert(c, d).is_none() {
+                queue.push_back(c);
+            }
+        }
+    }
+    while let Some(c) = queue.pop_front() {
+        let first = first_moves[&c];
+        if is_food(logic, &c) {
+            return first;
+        }
+        for d in DIRECTIONS {
+            if let Some(next) = safe_cell(logic, &c, &d) {
+                if next != head && !first_moves.contains_key(&next) {
+                    first_moves.insert(next, first);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    // No food can be reached. Survive as long as possible.
+    DIRECTIONS
+        .into_iter()
+        .filter(|d| *d != dir.opposite())
+        .find(|d| safe_cell(logic, &head, d).is_some())
+        .unwrap_or(dir)
+}
+
+/// The cell next to `c` toward the direction, if the snake can go there.
+/// The tail moves away unless the snake is growing, so it is safe if not.
+fn safe_cell(logic: &SnakeGameLogic, c: &Coord, dir: &Direction) -> Option<Coord> {
+    let next = logic.next_cell(c, dir)?;
+    let blocked = logic.obstacles.contains(&next)
+        || logic.bombs.contains(&next)
+        || logic.portal_exit(&next).is_some()
+        || logic.poison == Some(next)
+        || logic.reverse == Some(next)
+        || (logic.occupied.contains(&next)
+            && (logic.snakes[0].body.back() != Some(&next) || logic.snakes[0].growing > 0));
+    (!blocked).then_some(next)
+}
+
+fn is_food(logic: &SnakeGameLogic, c: &Coord) -> bool {
+    logic.feeds.contains(c) || logic.golden() == Some(*c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Size;
+
+    #[test]
+    fn autopilot_heads_for_feed() {
+        // The feed is below the head.
+        let mut logic = SnakeGameLogic::new(Size(20, 20), 1, 0);
+        logic.feeds = vec![Coord(4, 10)];
+        assert_eq!(autopilot(&logic), Direction::Down);
+    }
+
+    #[test]
+    fn autopilot_avoids_wall() {
+        // Moving right along the top row, with the feed behind the snake.
+        let mut logic = SnakeGameLogic::new(Size(20, 20), 1, 0);
+        logic.snakes[0].body = [Coord(18, 1), Coord(17, 1), Coord(16, 1)].into();
+        logic.occupied = logic.body().copied().collect();
+        logic.feeds = vec![Coord(1, 1)];
+        assert_eq!(autopilot(&logic), Direction::Down);
+    }
+
+    #[test]
+    fn autopilot_eats_many_feeds() {
+        let mut logic = SnakeGameLogic::new(Size(12, 12), 1, 0);
+        for _ in 0..2000 {
+            logic.set_dir(autopilot(&logic));
+            if logic.step().is_over() {
+                break;
+            }
+        }
+        assert!(logic.score() >= 10, "score: {}", logic.score());
+    }
+}
@@ -0,0 +1,318 @@
+//! Options of the command line.
+
+use super::Difficulty;
+use clap::Parser;
+use snake_game::{Coord, Direction, FeedWeights, DEFAULT_INITIAL_LEN, MIN_FIELD_LEN};
+use std::path::PathBuf;
+
+/// A snake game on the terminal.
+///
+/// Options which are not given take the settings of `~/.config/snake/config.toml`, and then of the difficulty.
+#[derive(Debug, Parser)]
+#[command(name = "snake-game", version, about)]
+pub struct Cli {
+    /// Start from a preset of the speed, the obstacles and the walls [default: normal]
+    #[arg(long, value_enum)]
+    pub difficulty: Option<Difficulty>,
+
+    /// Width of the field including walls [default: 20]
+    #[arg(long, value_name = "W", value_parser = field_len)]
+    pub width: Option<u16>,
+
+    /// Height of the field including walls [default: 20]
+    #[arg(long, value_name = "H", value_parser = field_len)]
+    pub height: Option<u16>,
+
+    /// Make the field as large as the terminal, instead of `--width` and `--height`
+    #[arg(long)]
+    pub fit: bool,
+
+    /// Go through the walls and appear from the opposite side
+    #[arg(long)]
+    pub wrap: bool,
+
+    /// Go through the left and right walls only
+    #[arg(long)]
+    pub wrap_x: bool,
+
+    /// Go through the top and bottom walls only
+    #[arg(long)]
+    pub wrap_y: bool,
+
+    /// Draw the board without colors
+    #[arg(long)]
+    pub no_color: bool,
+
+    /// Draw the walls with box-drawing characters
+    #[arg(long)]
+    pub unicode: bool,
+
+    /// Draw the snake in one color, instead of shifting the hue along the body
+    #[arg(long)]
+    pub no_gradient: bool,
+
+    /// Leave a faint trail for a few moves on the cells the tail leaves
+    #[arg(long)]
+    pub trail: bool,
+
+    /// Ring the terminal bell when the snake eats
+    #[arg(long, overrides_with = "no_sound")]
+    pub sound: bool,
+
+    /// Do not ring the bell, even if the config file turns it on
+    #[arg(long, overrides_with = "sound")]
+    pub no_sound: bool,
+
+    /// Interval between moves in milliseconds [default: 150]
+    #[arg(
+        long,
+        visible_alias = "speed",
+        value_name = "MS",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub tick_ms: Option<u64>,
+
+    /// Get faster by 5 ms per feed eaten, down to 60 ms
+    #[arg(long)]
+    pub accelerate: bool,
+
+    /// Seconds to count down before a game starts, or 0 to start at once
+    #[arg(long, value_name = "SECS", default_value_t = 3)]
+    pub countdown: u32,
+
+    /// Length of the snake at the start
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_INITIAL_LEN)]
+    pub length: usize,
+
+    /// Put the head at the cell heading DIR (up, down, left or right)
+    #[arg(long, value_name = "X,Y,DIR", value_parser = parse_start)]
+    pub start: Option<(Coord, Direction)>,
+
+    /// Put the first feed at the cell instead of the center
+    #[arg(long, value_name = "X,Y", value_parser = parse_coord)]
+    pub feed_at: Option<Coord>,
+
+    /// Start from a random place and direction chosen by the seed
+    #[arg(long)]
+    pub random_start: bool,
+
+    /// Number of obstacles inside the field [default: 0, or 15 on hard]
+    #[arg(long, value_name = "N")]
+    pub obstacles: Option<usize>,
+
+    /// Number of pairs of portals inside the field, which take the head from one to the other
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub portals: usize,
+
+    /// Number of feeds on the field at the same time
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = positive)]
+    pub feeds: usize,
+
+    /// Move the feeds to other cells if none is eaten in STEPS moves
+    #[arg(long, value_name = "STEPS", value_parser = positive)]
+    pub feed_ttl: Option<usize>,
+
+    /// Make each feed drift to a next cell every STEPS moves
+    #[arg(long, value_name = "STEPS", value_parser = positive)]
+    pub moving_food: Option<usize>,
+
+    /// Put a bomb on the field every STEPS moves, up to 5
+    #[arg(long, value_name = "STEPS", value_parser = positive)]
+    pub bombs: Option<usize>,
+
+    /// Number of crashes the snake survives, plus one
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = positive)]
+    pub lives: usize,
+
+    /// Moves at the start in which a crash does not end the game
+    #[arg(long, value_name = "STEPS", default_value_t = 0)]
+    pub grace: usize,
+
+    /// Segments the snake grows by for each feed
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = positive)]
+    pub growth: usize,
+
+    /// Multiply the points of feeds eaten within STEPS moves of each other, up to x5
+    #[arg(long, value_name = "STEPS", value_parser = positive)]
+    pub combo: Option<usize>,
+
+    /// Play on the map in FILE. Given more than once, the levels are played in order
+    #[arg(long, value_name = "FILE")]
+    pub level: Vec<PathBuf>,
+
+    /// Points to make in each level to go to the next one
+    #[arg(long, value_name = "N", default_value_t = 10, value_parser = positive)]
+    pub level_score: usize,
+
+    /// Put a poison feed on the field, which shrinks the snake
+    #[arg(long)]
+    pub poison: bool,
+
+    /// Let a golden feed worth 5 points appear sometimes
+    #[arg(long)]
+    pub golden: bool,
+
+    /// Put a feed on the field which reverses the controls for a while
+    #[arg(long)]
+    pub reverse: bool,
+
+    /// Let a star appear rarely, which lets the snake go through its body for a while
+    #[arg(long)]
+    pub star: bool,
+
+    /// Let an hourglass appear sometimes, which slows the game down for a while
+    #[arg(long)]
+    pub slow_mo: bool,
+
+    /// Weights of the kinds of a new feed, which is normal, golden or poison
+    #[arg(long, value_name = "FEED,GOLDEN,POISON", value_parser = parse_weights)]
+    pub feed_weights: Option<FeedWeights>,
+
+    /// Never put a new feed right in front of the head
+    #[arg(long)]
+    pub avoid_head: bool,
+
+    /// Seed of the random number generator, for the same feeds and obstacles every time
+    #[arg(long, value_name = "N")]
+    pub seed: Option<u64>,
+
+    /// Play the challenge of the day, with the seed made from the date
+    #[arg(long)]
+    pub daily: bool,
+
+    /// Score as much as possible in SECS seconds
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub time_attack: Option<u64>,
+
+    /// Save the recording of the game to FILE when it ends
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    /// Append every frame drawn to FILE as plain text
+    #[arg(long, value_name = "FILE")]
+    pub record_frames: Option<PathBuf>,
+
+    /// Write every frame drawn to FILE as an asciicast
+    #[arg(long, value_name = "FILE")]
+    pub export_cast: Option<PathBuf>,
+
+    /// Play the recorded game in FILE again
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+
+    /// Show the head of the snake of the recorded game in FILE
+    #[arg(long, value_name = "FILE")]
+    pub ghost: Option<PathBuf>,
+
+    /// Let the autopilot play
+    #[arg(long)]
+    pub ai: bool,
+
+    /// Two players share the keyboard
+    #[arg(long)]
+    pub two_player: bool,
+
+    /// Press u to undo moves. The high score is not saved
+    #[arg(long)]
+    pub practice: bool,
+
+    /// Never die. The score does not count
+    #[arg(long)]
+    pub zen: bool,
+
+    /// Play on an open field with the view following the head
+    #[arg(long)]
+    pub no_walls: bool,
+
+    /// Hide the controls shown below the field
+    #[arg(long)]
+    pub no_help: bool,
+
+    /// Start the game at once, without the menu
+    #[arg(long)]
+    pub no_menu: bool,
+
+    /// Print the statistics of every game played, and exit
+    #[arg(long)]
+    pub stats: bool,
+
+    /// Go on with the game quit last
+    #[arg(long)]
+    pub resume: bool,
+}
+
+fn field_len(value: &str) -> Result<u16, String> {
+    match value.parse() {
+        Ok(len) if len >= MIN_FIELD_LEN => Ok(len),
+        Ok(_) => Err(format!("must be at least {}", MIN_FIELD_LEN)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn positive(value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(0) => Err("must be at least 1".to_string()),
+        Ok(n) => Ok(n),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Weights of the normal, golden and poison feeds, such as `80,15,5`.
+fn parse_weights(value: &str) -> Result<FeedWeights, String> {
+    let error =
+        || "must be three weights `<FEED>,<GOLDEN>,<POISON>` and one of them must be positive";
+    let weights: Vec<u32> = value
+        .split(',')
+        .map(|w| w.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| error())?;
+    match weights[..] {
+        [feed, golden, poison] if feed > 0 || golden > 0 || poison > 0 => Ok(FeedWeights {
+            feed,
+            golden,
+            poison,
+        }),
+        _ => Err(error().to_string()),
+    }
+}
+
+/// Parse `<X>,<Y>` into a cell.
+fn parse_coord(value: &str) -> Result<Coord, String> {
+    match value
+        .split(',')
+        .map(|n| n.trim().parse())
+        .collect::<Vec<_>>()[..]
+    {
+        [Ok(x), Ok(y)] => Ok(Coord(x, y)),
+        _ => Err("must be a cell `<X>,<Y>`".to_string()),
+    }
+}
+
+/// Parse `<X>,<Y>,<DIR>` into the cell and direction of the head.
+fn parse_start(value: &str) -> Result<(Coord, Direction), String> {
+    let error = || "must be `<X>,<Y>,<DIR>` with DIR one of up, down, left and right".to_string();
+    let (cell, dir) = value.rsplit_once(',').ok_or_else(error)?;
+    let dir = match dir.trim().to_ascii_lowercase().as_str() {
+        "up" => Direction::Up,
+        "down" => Direction::Down,
+        "left" => Direction::Left,
+        "right" => Direction::Right,
+        _ => return Err(error()),
+    };
+    let cell = parse_coord(cell).map_err(|_| error())?;
+    Ok((cell, dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn options_are_consistent() {
+        Cli::command().debug_assert();
+        assert_eq!(parse_start("1, 2, Up"), Ok((Coord(1, 2), Direction::Up)));
+        assert!(parse_start("1,up").is_err());
+        assert!(parse_weights("0,0,0").is_err());
+    }
+}
@@ -0,0 +1,110 @@
+//! Settings read from the config file.
+
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Contents of `~/.config/snake/config.toml`.
+/// Every setting is optional, and a missing one keeps its default.
+/// Command line options take priority over the file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub width: Option<u16>,
+    pub height: Option<u16>,
+    pub tick_ms: Option<u64>,
+    pub wrap: Option<bool>,
+    pub color: Option<bool>,
+    pub unicode: Option<bool>,
+    pub sound: Option<bool>,
+    pub keys: Keys,
+}
+
+/// Names of the keys bound to each action, such as `"k"`, `"Up"` or `"Space"`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Keys {
+    pub up: Option<Vec<String>>,
+    pub down: Option<Vec<String>>,
+    pub left: Option<Vec<String>>,
+    pub right: Option<Vec<String>>,
+    pub pause: Option<Vec<String>>,
+    pub restart: Option<Vec<String>>,
+    pub slower: Option<Vec<String>>,
+    pub faster: Option<Vec<String>>,
+    pub step: Option<Vec<String>>,
+    pub undo: Option<Vec<String>>,
+    pub help: Option<Vec<String>>,
+    pub controls: Option<Vec<String>>,
+    pub select: Option<Vec<String>>,
+    pub quit: Option<Vec<String>>,
+    pub yes: Option<Vec<String>>,
+    pub no: Option<Vec<String>>,
+}
+
+impl Config {
+    /// Load the config file.
+    /// It is fine not to have one, but a broken one is an error.
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("failed to read `{}`: {}", path.display(), e)),
+        };
+        Self::parse(&text).map_err(|e| format!("invalid config `{}`: {}", path.display(), e))
+    }
+
+    fn parse(text: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    /// `$XDG_CONFIG_HOME/snake/config.toml`, or `~/.config/snake/config.toml`.
+    fn path() -> Option<PathBuf> {
+        let dir = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(dir.join("snake").join("config.toml"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_config_is_default() {
+        let config = Config::parse("").unwrap();
+        assert_eq!(config.width, None);
+        assert!(config.keys.up.is_none());
+    }
+
+    #[test]
+    fn parse_config() {
+        let config = Config::parse(
+            r#"
+            width = 40
+            wrap = true
+
+            [keys]
+            up = ["i", "Up"]
+            quit = ["Esc"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.width, Some(40));
+        assert_eq!(config.height, None);
+        assert_eq!(config.wrap, Some(true));
+        assert_eq!(config.keys.up, Some(vec!["i".into(), "Up".into()]));
+        assert_eq!(config.keys.quit, Some(vec!["Esc".into()]));
+    }
+
+    #[test]
+    fn unknown_setting_is_error() {
+        assert!(Config::parse("widht = 40").is_err());
+    }
+}
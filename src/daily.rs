@@ -0,0 +1,48 @@
+//! The daily challenge, which is the same game for everyone on the same day.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Today in UTC as `YYYY-MM-DD`, so that the day changes at the same time everywhere.
+pub fn today() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    date(secs / 86400)
+}
+
+/// The date of the day counted from 1970-01-01.
+fn date(days: u64) -> String {
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z % 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + u64::from(m <= 2);
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+/// The seed of the challenge of the date.
+/// It is the FNV-1a hash of the date, which does not change between platforms or versions.
+pub fn seed(date: &str) -> u64 {
+    date.bytes().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn date_of_days() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(11016), "2000-02-29");
+        assert_eq!(date(20740), "2026-10-14");
+        assert_eq!(seed("2026-10-14"), seed("2026-10-14"));
+        assert_ne!(seed("2026-10-14"), seed("2026-10-15"));
+    }
+}
@@ -0,0 +1,484 @@
+//! Sources of the player's input.
+
+use super::config::Keys;
+use super::render::Prompts;
+use super::SnakeGameEvent;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use snake_game::Direction;
+use std::time::{Duration, Instant};
+
+/// What an input source gives.
+pub enum Input {
+    Event(SnakeGameEvent),
+    /// Nothing happened in time.
+    Timeout,
+    /// There is no more input.
+    End,
+}
+
+/// Where the events of the game come from.
+///
+/// Any iterator of events is an input source,
+/// so a game can be driven by a scripted sequence of events.
+/// It gives the events at once and never times out.
+pub trait InputSource {
+    /// Wait for the next event for up to `timeout`, or without a limit if it is None.
+    fn poll_event(&mut self, timeout: Option<Duration>) -> Input;
+
+    /// Give the characters typed as [`SnakeGameEvent::Typed`] instead of the bound events, while `on` is true.
+    /// Enter still selects and Esc quits.
+    fn set_text_mode(&mut self, _on: bool) {}
+}
+
+impl<I> InputSource for I
+where
+    I: Iterator<Item = SnakeGameEvent>,
+{
+    fn poll_event(&mut self, _: Option<Duration>) -> Input {
+        self.next().map_or(Input::End, Input::Event)
+    }
+}
+
+/// Keys pressed on the terminal.
+pub struct TermInput {
+    keymap: Keymap,
+    /// Give the characters typed instead of the bound events.
+    text_mode: bool,
+}
+
+impl TermInput {
+    pub fn new(keymap: Keymap) -> Self {
+        Self {
+            keymap,
+            text_mode: false,
+        }
+    }
+
+    /// The event of a key while typing text.
+    fn text_event(&self, key: KeyCode) -> Option<SnakeGameEvent> {
+        use SnakeGameEvent::*;
+
+        match key {
+            KeyCode::Char(c) => Some(Typed(c)),
+            KeyCode::Backspace => Some(Erase),
+            KeyCode::Enter => Some(Select),
+            KeyCode::Esc => Some(Quit),
+            // Other keys which are not characters select or quit as they are bound to.
+            _ => self.keymap.get(key).filter(|e| matches!(e, Select | Quit)),
+        }
+    }
+}
+
+impl InputSource for TermInput {
+    fn poll_event(&mut self, timeout: Option<Duration>) -> Input {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            // Keys which are not bound do not extend the wait.
+            if let Some(deadline) = deadline {
+                match event::poll(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(true) => {}
+                    Ok(false) => return Input::Timeout,
+                    Err(_) => return Input::End,
+                }
+            }
+            let Ok(event) = event::read() else {
+                return Input::End;
+            };
+            match event {
+                // The raw mode turns Ctrl-C into a key, which ends the game as a signal would.
+                Event::Key(key)
+                    if key.code == KeyCode::Char('c')
+                        && key.modifiers.contains(KeyModifiers::CONTROL) =>
+                {
+                    return Input::End
+                }
+                // Some platforms also report key releases.
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    let event = if self.text_mode {
+                        self.text_event(key.code)
+                    } else {
+                        self.keymap.get(key.code)
+                    };
+                    if let Some(event) = event {
+                        return Input::Event(event);
+                    }
+                }
+                Event::Resize(cols, rows) => {
+                    return Input::Event(SnakeGameEvent::Resize(cols, rows))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn set_text_mode(&mut self, on: bool) {
+        self.text_mode = on;
+    }
+}
+
+/// Map from keys to the events of the game.
+/// The keys are kept in the order they are bound, so the help bar shows the first key of an action.
+pub struct Keymap(Vec<(KeyCode, SnakeGameEvent)>);
+
+impl Default for Keymap {
+    fn default() -> Self {
+        use KeyCode::*;
+        use SnakeGameEvent::*;
+
+        let mut keymap = Self(Vec::new());
+        for (keys, event) in [
+            (&[Char('k'), Char('w'), Up][..], ChangeDir(Direction::Up)),
+            (&[Char('j'), Char('s'), Down], ChangeDir(Direction::Down)),
+            (&[Char('h'), Char('a'), Left], ChangeDir(Direction::Left)),
+            (&[Char('l'), Char('d'), Right], ChangeDir(Direction::Right)),
+            (&[Char('p'), Char(' ')], TogglePause),
+            (&[Char('r')], Restart),
+            (&[Char('[')], Slower),
+            (&[Char(']')], Faster),
+            (&[Char('.')], StepOnce),
+            (&[Char('u')], Undo),
+            (&[Tab], ToggleHelp),
+            (&[Char('?')], ShowControls),
+            (&[Enter], Select),
+            (&[Char('q'), Esc], Quit),
+            (&[Char('y')], Yes),
+            (&[Char('n')], No),
+        ] {
+            for &key in keys {
+                keymap.bind(key, event);
+            }
+        }
+        keymap
+    }
+}
+
+impl Keymap {
+    /// Replace the default keys of the actions given in the config.
+    pub fn from_config(keys: &Keys) -> Result<Self, String> {
+        use SnakeGameEvent::*;
+
+        let mut keymap = Self::default();
+        for (names, event) in [
+            (&keys.up, ChangeDir(Direction::Up)),
+            (&keys.down, ChangeDir(Direction::Down)),
+            (&keys.left, ChangeDir(Direction::Left)),
+            (&keys.right, ChangeDir(Direction::Right)),
+            (&keys.pause, TogglePause),
+            (&keys.restart, Restart),
+            (&keys.slower, Slower),
+            (&keys.faster, Faster),
+            (&keys.step, StepOnce),
+            (&keys.undo, Undo),
+            (&keys.help, ToggleHelp),
+            (&keys.controls, ShowControls),
+            (&keys.select, Select),
+            (&keys.quit, Quit),
+            (&keys.yes, Yes),
+            (&keys.no, No),
+        ] {
+            if let Some(names) = names {
+                keymap.unbind(event);
+                for name in names {
+                    keymap.bind(parse_key(name)?, event);
+                }
+            }
+        }
+        Ok(keymap)
+    }
+
+    /// Give the arrow keys to the second player.
+    /// The first player keeps the other keys.
+    pub fn split_for_two_players(&mut self) {
+        use KeyCode::*;
+        use SnakeGameEvent::ChangeDir2;
+
+        for (key, dir) in [
+            (Up, Direction::Up),
+            (Down, Direction::Down),
+            (Left, Direction::Left),
+            (Right, Direction::Right),
+        ] {
+            self.bind(key, ChangeDir2(dir));
+        }
+    }
+
+    /// Make the key send the event, instead of what it was bound to.
+    pub fn bind(&mut self, key: KeyCode, event: SnakeGameEvent) {
+        let key = lowercase(key);
+        self.0.retain(|(k, _)| *k != key);
+        self.0.push((key, event));
+    }
+
+    /// Remove every key bound to the event.
+    pub fn unbind(&mut self, event: SnakeGameEvent) {
+        self.0.retain(|(_, e)| *e != event);
+    }
+
+    /// A line for each action with the keys bound to it, for the panel of the controls.
+    pub fn describe(&self) -> Vec<String> {
+        use SnakeGameEvent::*;
+
+        let actions = [
+            ("up", ChangeDir(Direction::Up)),
+            ("down", ChangeDir(Direction::Down)),
+            ("left", ChangeDir(Direction::Left)),
+            ("right", ChangeDir(Direction::Right)),
+            ("P2 up", ChangeDir2(Direction::Up)),
+            ("P2 down", ChangeDir2(Direction::Down)),
+            ("P2 left", ChangeDir2(Direction::Left)),
+            ("P2 right", ChangeDir2(Direction::Right)),
+            ("pause", TogglePause),
+            ("restart", Restart),
+            ("slower", Slower),
+            ("faster", Faster),
+            ("step", StepOnce),
+            ("undo", Undo),
+            ("help bar", ToggleHelp),
+            ("controls", ShowControls),
+            ("select", Select),
+            ("quit", Quit),
+            ("yes", Yes),
+            ("no", No),
+        ];
+        actions
+            .into_iter()
+            .filter_map(|(label, event)| {
+                let mut keys: Vec<String> = (self.0.iter())
+                    .filter(|(_, e)| *e == event)
+                    .map(|(key, _)| key_name(*key))
+                    .collect();
+                keys.sort();
+                (!keys.is_empty()).then(|| format!("{:<9} {}", label, keys.join(" ")))
+            })
+            .collect()
+    }
+
+    /// The line of the main controls for the help bar, with the first key bound to each action.
+    pub fn help_line(&self) -> String {
+        use SnakeGameEvent::*;
+
+        let first_key = |event| self.first_key(event, |_| true);
+        let mut items = Vec::new();
+        let moves: Option<Vec<String>> = [
+            Direction::Left,
+            Direction::Down,
+            Direction::Up,
+            Direction::Right,
+        ]
+        .into_iter()
+        .map(|dir| first_key(ChangeDir(dir)))
+        .collect();
+        if let Some(keys) = moves {
+            // Single characters are put together as in `hjkl`.
+            let sep = if keys.iter().all(|k| k.chars().count() == 1) {
+                ""
+            } else {
+                "/"
+            };
+            items.push(format!("{}: move", keys.join(sep)));
+        }
+        for (label, event) in [
+            ("pause", TogglePause),
+            ("restart", Restart),
+            ("quit", Quit),
+            ("all keys", ShowControls),
+            ("hide this", ToggleHelp),
+        ] {
+            if let Some(key) = first_key(event) {
+                items.push(format!("{}: {}", key, label));
+            }
+        }
+        items.join("  ")
+    }
+
+    /// The keys to answer the messages over the field, with the first key bound to each action.
+    pub fn prompts(&self) -> Prompts {
+        use SnakeGameEvent::*;
+
+        let key = |event, default: &str| {
+            self.first_key(event, |_| true)
+                .unwrap_or_else(|| default.to_string())
+        };
+        // Only the keys which are not characters work while typing, and backspace erases.
+        let special_key = |event, default: &str| {
+            self.first_key(event, |key| {
+                !matches!(key, KeyCode::Char(_) | KeyCode::Backspace)
+            })
+            .unwrap_or_else(|| default.to_string())
+        };
+        Prompts {
+            name: format!(
+                " {}: save  {}: skip ",
+                special_key(Select, "enter"),
+                special_key(Quit, "esc")
+            ),
+            over: format!(" {}: restart  {}: quit ", key(Restart, "-"), key(Quit, "-")),
+            quit: format!(" Quit? ({}/{}) ", key(Yes, "-"), key(No, "-")),
+        }
+    }
+
+    /// The name of the first key bound to the event among the keys which match.
+    fn first_key(
+        &self,
+        event: SnakeGameEvent,
+        filter: impl Fn(&KeyCode) -> bool,
+    ) -> Option<String> {
+        (self.0.iter())
+            .find(|(key, e)| *e == event && filter(key))
+            .map(|(key, _)| key_name(*key))
+    }
+
+    /// The event the key is bound to.
+    fn get(&self, key: KeyCode) -> Option<SnakeGameEvent> {
+        let key = lowercase(key);
+        self.0.iter().find(|(k, _)| *k == key).map(|(_, e)| *e)
+    }
+}
+
+/// Treat upper case the same so that caps lock does not break the controls.
+fn lowercase(key: KeyCode) -> KeyCode {
+    match key {
+        KeyCode::Char(c) => KeyCode::Char(c.to_ascii_lowercase()),
+        _ => key,
+    }
+}
+
+/// Parse the name of a key in the config: a single character, or a name such as `Up` or `Space`.
+fn parse_key(name: &str) -> Result<KeyCode, String> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(lowercase(KeyCode::Char(c)));
+    }
+    match name.to_ascii_lowercase().as_str() {
+        "up" => Ok(KeyCode::Up),
+        "down" => Ok(KeyCode::Down),
+        "left" => Ok(KeyCode::Left),
+        "right" => Ok(KeyCode::Right),
+        "space" => Ok(KeyCode::Char(' ')),
+        "enter" => Ok(KeyCode::Enter),
+        "esc" => Ok(KeyCode::Esc),
+        "tab" => Ok(KeyCode::Tab),
+        "backspace" => Ok(KeyCode::Backspace),
+        _ => Err(format!("unknown key `{}`", name)),
+    }
+}
+
+/// The name of a key as the config takes it.
+fn key_name(key: KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Up => "up".to_string(),
+        KeyCode::Down => "down".to_string(),
+        KeyCode::Left => "left".to_string(),
+        KeyCode::Right => "right".to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Esc => "esc".to_string(),
+        KeyCode::Tab => "tab".to_string(),
+        KeyCode::Backspace => "backspace".to_string(),
+        key => format!("{:?}", key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_keymap() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            keymap.get(KeyCode::Char('k')),
+            Some(SnakeGameEvent::ChangeDir(Direction::Up))
+        );
+        // Caps lock does not matter.
+        assert_eq!(
+            keymap.get(KeyCode::Char('L')),
+            Some(SnakeGameEvent::ChangeDir(Direction::Right))
+        );
+        assert_eq!(keymap.get(KeyCode::Char('q')), Some(SnakeGameEvent::Quit));
+        assert_eq!(keymap.get(KeyCode::Esc), Some(SnakeGameEvent::Quit));
+        assert_eq!(keymap.get(KeyCode::Char('z')), None);
+    }
+
+    #[test]
+    fn describe_keys() {
+        let lines = Keymap::default().describe();
+        assert_eq!(lines[0], "up        k up w");
+        assert!(lines.contains(&"pause     p space".to_string()));
+        assert!(!lines.iter().any(|line| line.starts_with("P2")));
+    }
+
+    #[test]
+    fn help_line_follows_keys() {
+        assert_eq!(
+            Keymap::default().help_line(),
+            "hjkl: move  p: pause  r: restart  q: quit  ?: all keys  tab: hide this"
+        );
+        let keys = Keys {
+            left: Some(vec!["Left".into()]),
+            quit: Some(vec!["X".into(), "Esc".into()]),
+            controls: Some(Vec::new()),
+            ..Keys::default()
+        };
+        assert_eq!(
+            Keymap::from_config(&keys).unwrap().help_line(),
+            "left/j/k/l: move  p: pause  r: restart  x: quit  tab: hide this"
+        );
+    }
+
+    #[test]
+    fn prompts_follow_keys() {
+        let prompts = Keymap::default().prompts();
+        assert_eq!(prompts.name, " enter: save  esc: skip ");
+        assert_eq!(prompts.over, " r: restart  q: quit ");
+        assert_eq!(prompts.quit, " Quit? (y/n) ");
+
+        let keys = Keys {
+            quit: Some(vec!["X".into(), "Tab".into()]),
+            yes: Some(vec!["Enter".into()]),
+            ..Keys::default()
+        };
+        let prompts = Keymap::from_config(&keys).unwrap().prompts();
+        assert_eq!(prompts.name, " enter: save  tab: skip ");
+        assert_eq!(prompts.over, " r: restart  x: quit ");
+        assert_eq!(prompts.quit, " Quit? (enter/n) ");
+    }
+
+    #[test]
+    fn second_player_takes_arrows() {
+        let mut keymap = Keymap::default();
+        keymap.split_for_two_players();
+        assert_eq!(
+            keymap.get(KeyCode::Up),
+            Some(SnakeGameEvent::ChangeDir2(Direction::Up))
+        );
+        assert_eq!(
+            keymap.get(KeyCode::Char('k')),
+            Some(SnakeGameEvent::ChangeDir(Direction::Up))
+        );
+    }
+
+    #[test]
+    fn config_replaces_keys_of_action() {
+        let keys = Keys {
+            quit: Some(vec!["Esc".into(), "X".into()]),
+            ..Keys::default()
+        };
+        let keymap = Keymap::from_config(&keys).unwrap();
+        assert_eq!(keymap.get(KeyCode::Char('q')), None);
+        assert_eq!(keymap.get(KeyCode::Esc), Some(SnakeGameEvent::Quit));
+        assert_eq!(keymap.get(KeyCode::Char('x')), Some(SnakeGameEvent::Quit));
+        // Other actions keep the defaults.
+        assert_eq!(
+            keymap.get(KeyCode::Char('p')),
+            Some(SnakeGameEvent::TogglePause)
+        );
+
+        let keys = Keys {
+            up: Some(vec!["Nope".into()]),
+            ..Keys::default()
+        };
+        assert!(Keymap::from_config(&keys).is_err());
+    }
+}
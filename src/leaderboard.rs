@@ -0,0 +1,86 @@
+//! The best scores with the names of the players, kept across sessions.
+
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Number of scores kept.
+pub const LEN: usize = 10;
+
+/// Longest name a player can enter.
+pub const MAX_NAME_LEN: usize = 10;
+
+/// Scores stored in `~/.snake_leaderboard.json`, the best first.
+#[derive(Debug, Default)]
+pub struct Leaderboard {
+    /// Where to save. Nothing is saved if None.
+    path: Option<PathBuf>,
+    entries: Vec<Entry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    pub score: usize,
+}
+
+impl Leaderboard {
+    /// Load the leaderboard.
+    /// A missing or broken file is treated as no scores.
+    pub fn load() -> Self {
+        let path =
+            env::var_os("HOME").map(|home| PathBuf::from(home).join(".snake_leaderboard.json"));
+        let entries = path
+            .as_ref()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { path, entries }
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Return true if the score gets on the leaderboard.
+    pub fn qualifies(&self, score: usize) -> bool {
+        score > 0 && (self.entries.len() < LEN || self.entries.iter().any(|e| e.score < score))
+    }
+
+    /// Put the score on the leaderboard, and save it.
+    /// It goes below the same scores made before.
+    pub fn insert(&mut self, name: String, score: usize) {
+        let i = self.entries.partition_point(|e| e.score >= score);
+        self.entries.insert(i, Entry { name, score });
+        self.entries.truncate(LEN);
+        if let (Some(path), Ok(text)) = (&self.path, serde_json::to_string_pretty(&self.entries)) {
+            // Failing to save must not end the game.
+            let _ = fs::write(path, text + "\n");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keep_best_scores() {
+        let mut board = Leaderboard::default();
+        assert!(!board.qualifies(0));
+        for score in 1..=LEN {
+            assert!(board.qualifies(score));
+            board.insert(format!("p{}", score), score);
+        }
+        assert!(!board.qualifies(1));
+        assert!(board.qualifies(2));
+
+        board.insert("new".to_string(), 5);
+        let names: Vec<&str> = board.entries().iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["p10", "p9", "p8", "p7", "p6", "p5", "new", "p4", "p3", "p2"]
+        );
+    }
+}
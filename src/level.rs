@@ -0,0 +1,185 @@
+//! Fields drawn as text.
+
+use crate::{Coord, Direction, Size, MIN_FIELD_LEN};
+use serde::{Deserialize, Serialize};
+use std::iter;
+
+/// A field loaded from a map, with one line per row including the walls.
+///
+/// - `#` is a wall, or an obstacle inside the field.
+/// - `@` is a feed at the start.
+/// - `^`, `v`, `<` or `>` is the head of the snake, which heads that way. The body lies behind it.
+/// - A digit from `1` to `9` is a portal, which leads to the other one of the same digit.
+/// - A space or `.` is an empty cell.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    size: Size,
+    obstacles: Vec<Coord>,
+    feeds: Vec<Coord>,
+    #[serde(default)]
+    portals: Vec<(Coord, Coord)>,
+    head: Coord,
+    dir: Direction,
+}
+
+impl Level {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let lines: Vec<&str> = text.trim_end().lines().collect();
+        let w = lines.first().map_or(0, |line| line.chars().count());
+        let h = lines.len();
+        if w < MIN_FIELD_LEN as usize || h < MIN_FIELD_LEN as usize {
+            return Err(format!(
+                "the map must be at least {}x{} (got {}x{})",
+                MIN_FIELD_LEN, MIN_FIELD_LEN, w, h
+            ));
+        }
+        if w > u16::MAX as usize || h > u16::MAX as usize {
+            return Err(format!("the map is too large ({}x{})", w, h));
+        }
+
+        let mut obstacles = Vec::new();
+        let mut feeds = Vec::new();
+        let mut ends = Vec::new();
+        let mut head = None;
+        for (y, line) in lines.iter().enumerate() {
+            if line.chars().count() != w {
+                return Err(format!(
+                    "line {} is not as wide as the first line ({})",
+                    y + 1,
+                    w
+                ));
+            }
+            for (x, c) in line.chars().enumerate() {
+                let p = Coord(x as u16, y as u16);
+                let border = x == 0 || y == 0 || x == w - 1 || y == h - 1;
+                if border {
+                    if c != '#' {
+                        return Err(format!("line {} has a hole in the walls", y + 1));
+                    }
+                    continue;
+                }
+                let dir = match c {
+                    '#' => {
+                        obstacles.push(p);
+                        continue;
+                    }
+                    '@' => {
+                        feeds.push(p);
+                        continue;
+                    }
+                    '1'..='9' => {
+                        ends.push((c, p));
+                        continue;
+                    }
+                    ' ' | '.' => continue,
+                    '^' => Direction::Up,
+                    'v' => Direction::Down,
+                    '<' => Direction::Left,
+                    '>' => Direction::Right,
+                    _ => return Err(format!("unknown character `{}` on line {}", c, y + 1)),
+                };
+                if head.replace((p, dir)).is_some() {
+                    return Err("the map has more than one snake".to_string());
+                }
+            }
+        }
+        let Some((head, dir)) = head else {
+            return Err("the map has no snake".to_string());
+        };
+        ends.sort_by_key(|&(c, _)| c);
+        let mut portals = Vec::new();
+        for pair in ends.chunk_by(|a, b| a.0 == b.0) {
+            match pair {
+                [(_, a), (_, b)] => portals.push((*a, *b)),
+                _ => return Err(format!("the portal `{}` is not a pair", pair[0].0)),
+            }
+        }
+
+        Ok(Self {
+            size: Size(w as u16, h as u16),
+            obstacles,
+            feeds,
+            portals,
+            head,
+            dir,
+        })
+    }
+
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    pub fn obstacles(&self) -> &[Coord] {
+        &self.obstacles
+    }
+
+    pub fn feeds(&self) -> &[Coord] {
+        &self.feeds
+    }
+
+    /// Pairs of portals, in the order of their digits.
+    pub fn portals(&self) -> &[(Coord, Coord)] {
+        &self.portals
+    }
+
+    /// Cell and direction of the head at the start.
+    pub fn start(&self) -> (Coord, Direction) {
+        (self.head, self.dir)
+    }
+
+    /// The longest snake which fits behind the head, on empty cells.
+    pub fn max_initial_len(&self) -> usize {
+        let Size(w, h) = self.size;
+        let back = self.dir.opposite();
+        iter::successors(Some(self.head), |c| c.adjascent(&back))
+            .take_while(|c| {
+                (1..w - 1).contains(&c.0)
+                    && (1..h - 1).contains(&c.1)
+                    && !self.obstacles.contains(c)
+                    && !self.feeds.contains(c)
+                    && !self.portals.iter().any(|&(a, b)| a == *c || b == *c)
+            })
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_level() {
+        let level = Level::parse(
+            "\
+########
+#.@....#
+#..#...#
+#...<..#
+#1....1#
+########
+",
+        )
+        .unwrap();
+        assert_eq!(level.size(), Size(8, 6));
+        assert_eq!(level.obstacles(), [Coord(3, 2)]);
+        assert_eq!(level.feeds(), [Coord(2, 1)]);
+        assert_eq!(level.portals(), [(Coord(1, 4), Coord(6, 4))]);
+        assert_eq!(level.start(), (Coord(4, 3), Direction::Left));
+        assert_eq!(level.max_initial_len(), 3);
+    }
+
+    #[test]
+    fn broken_level_is_error() {
+        let map = |inner: &str| format!("######\n{}\n#    #\n#    #\n#    #\n######", inner);
+        assert!(Level::parse(&map("# >  #")).is_ok());
+        assert!(Level::parse(&map("# >   ")).is_err());
+        assert!(Level::parse(&map("# >  ")).is_err());
+        assert!(Level::parse(&map("# >x #")).is_err());
+        assert!(Level::parse(&map("# >> #")).is_err());
+        assert!(Level::parse(&map("#1> 1#")).is_ok());
+        assert!(Level::parse(&map("#1>  #")).is_err());
+        assert!(Level::parse(&map("#1>11#")).is_err());
+        assert!(Level::parse(&map("#    #")).is_err());
+        assert!(Level::parse("#####\n#>  #\n#####").is_err());
+    }
+}
@@ -0,0 +1,2612 @@
+//! Logic of the snake game, independent of any front-end.
+
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::iter;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+mod autopilot;
+mod level;
+
+pub use autopilot::autopilot;
+pub use level::Level;
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub fn opposite(&self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+}
+
+/// Something to eat on the field.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Food {
+    Feed,
+    Golden,
+    Poison,
+    /// Swaps the controls for a while.
+    Reverse,
+    /// Lets the snake go through its body for a while.
+    Star,
+    /// Slows the game down for a while.
+    Slow,
+}
+
+impl Food {
+    /// Change of the score when the food is eaten.
+    pub fn points(&self) -> isize {
+        match self {
+            Food::Feed => 1,
+            Food::Golden => 5,
+            Food::Poison => -1,
+            Food::Reverse | Food::Star | Food::Slow => 0,
+        }
+    }
+
+    /// Change of the length of the snake when the food is eaten.
+    pub fn growth(&self) -> isize {
+        match self {
+            Food::Feed | Food::Golden => 1,
+            Food::Poison => -1,
+            Food::Reverse | Food::Star | Food::Slow => 0,
+        }
+    }
+}
+
+/// Kind of a new feed put on the field.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FeedKind {
+    Normal,
+    /// Worth 5 points, and disappears if it is not eaten in time.
+    Golden,
+    /// Shrinks the snake, and disappears if it is not eaten in time.
+    Poison,
+}
+
+impl FeedKind {
+    const ALL: [FeedKind; 3] = [FeedKind::Normal, FeedKind::Golden, FeedKind::Poison];
+
+    /// The food the feed is when eaten.
+    pub fn food(&self) -> Food {
+        match self {
+            FeedKind::Normal => Food::Feed,
+            FeedKind::Golden => Food::Golden,
+            FeedKind::Poison => Food::Poison,
+        }
+    }
+}
+
+/// How often each kind of feed is chosen for a new feed, relative to each other.
+/// A normal feed is chosen instead if a golden or poison feed is on the field already.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FeedWeights {
+    pub feed: u32,
+    pub golden: u32,
+    pub poison: u32,
+}
+
+impl FeedWeights {
+    /// Weight of the kind of feed.
+    pub fn of(&self, kind: FeedKind) -> u32 {
+        match kind {
+            FeedKind::Normal => self.feed,
+            FeedKind::Golden => self.golden,
+            FeedKind::Poison => self.poison,
+        }
+    }
+}
+
+impl Default for FeedWeights {
+    /// Only normal feeds appear.
+    fn default() -> Self {
+        Self {
+            feed: 85,
+            golden: 0,
+            poison: 0,
+        }
+    }
+}
+
+/// What happened at a step.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MoveResult {
+    /// The game goes on.
+    Alive,
+    /// The snake ate the food, and the game goes on.
+    Ate(Food),
+    /// The head hit a wall. The game is over.
+    HitWall,
+    /// The head hit an obstacle. The game is over.
+    HitObstacle,
+    /// The head touched a bomb. The game is over.
+    HitBomb,
+    /// The head hit the body. The game is over.
+    HitSelf,
+    /// The head hit the snake of the other player. The game is over.
+    HitSnake,
+    /// The snake crashed, and started again with one life less.
+    LostLife,
+    /// The snake ate poison while it was as short as at the start. The game is over.
+    Poisoned,
+    /// The snake filled the field and there is nothing left to eat.
+    Won,
+}
+
+impl MoveResult {
+    /// Return true if the game does not go on.
+    pub fn is_over(&self) -> bool {
+        !matches!(
+            self,
+            MoveResult::Alive | MoveResult::Ate(_) | MoveResult::LostLife
+        )
+    }
+}
+
+/// Something the game did, sent to the observers of [`SnakeGameLogic::subscribe`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum GameEvent {
+    /// The head of the snake of the player moved to the cell.
+    Moved { player: usize, head: Coord },
+    /// The snake of the player ate the food.
+    Ate { player: usize, food: Food },
+    /// The tail of the snake of the player stayed, and the snake is `len` cells long.
+    Grew { player: usize, len: usize },
+    /// The game is over for the reason.
+    GameOver(MoveResult),
+    /// The snake filled the field.
+    Won,
+}
+
+/// Smallest width and height of the field, including walls.
+/// The starting snake and feed have to fit inside the walls.
+pub const MIN_FIELD_LEN: u16 = 6;
+
+/// Largest number of bombs on the field at the same time.
+pub const MAX_BOMBS: usize = 5;
+
+/// Highest multiplier of the points by combos.
+pub const MAX_COMBO: usize = 5;
+
+/// Length of the snake at the start, unless another one is given.
+pub const DEFAULT_INITIAL_LEN: usize = 3;
+
+fn assert_field_size(field_size: Size) {
+    assert!(
+        field_size.0 >= MIN_FIELD_LEN && field_size.1 >= MIN_FIELD_LEN,
+        "the field must be at least {}x{} (got {}x{})",
+        MIN_FIELD_LEN,
+        MIN_FIELD_LEN,
+        field_size.0,
+        field_size.1
+    );
+}
+
+/// The longest snake which can start on a field of the width.
+/// The tail starts on the 2nd column inside the walls, as the first one is left free.
+pub fn max_initial_len(width: u16) -> usize {
+    width.saturating_sub(3) as usize
+}
+
+/// Where the snake and the feed are at the start of a game.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Start {
+    pub head: Coord,
+    /// The direction the snake moves. The body lies straight behind the head.
+    pub dir: Direction,
+    pub feed: Coord,
+}
+
+impl Start {
+    /// The snake lies straight on the 1st row inside the walls heading right, and the feed is at the center.
+    pub fn new(field_size: Size, len: usize) -> Self {
+        Self {
+            head: Coord(len as u16 + 1, 2),
+            dir: Direction::Right,
+            feed: Coord(field_size.0 / 2, field_size.1 / 2),
+        }
+    }
+
+    /// A random start on the field, chosen by the seed.
+    /// The snake heads toward a free cell as at the default start.
+    ///
+    /// # Panics
+    /// Panics if `len` is 0 or more than [`max_initial_len`].
+    pub fn random(field_size: Size, len: usize, seed: u64) -> Self {
+        assert!(
+            len >= 1 && len <= max_initial_len(field_size.0),
+            "a snake of length {} does not fit in the width {}",
+            len,
+            field_size.0
+        );
+        let mut rng = StdRng::seed_from_u64(seed);
+        let max = Size(field_size.0 - 2, field_size.1 - 2);
+        // Draw again until it fits. A snake heading left or right always fits somewhere.
+        loop {
+            let dir = *[
+                Direction::Up,
+                Direction::Down,
+                Direction::Left,
+                Direction::Right,
+            ]
+            .choose(&mut rng)
+            .unwrap();
+            let start = Self {
+                head: Coord::rand(&mut rng, &Size(1, 1), &max),
+                dir,
+                feed: Coord::rand(&mut rng, &Size(1, 1), &max),
+            };
+            let free_ahead = (start.head.adjascent(&dir))
+                .is_some_and(|c| (1..=max.0).contains(&c.0) && (1..=max.1).contains(&c.1));
+            if free_ahead && start.check(field_size, len).is_ok() {
+                return start;
+            }
+        }
+    }
+
+    /// Return an error if the snake of `len` cells or the feed is not inside the walls, or the feed is on the snake.
+    pub fn check(&self, field_size: Size, len: usize) -> Result<(), String> {
+        let Size(w, h) = field_size;
+        let inside = |c: &Coord| (1..w - 1).contains(&c.0) && (1..h - 1).contains(&c.1);
+        let back = self.dir.opposite();
+        let body: Vec<Coord> = iter::successors(Some(self.head), |c| c.adjascent(&back))
+            .take(len)
+            .collect();
+        if body.len() < len || !body.iter().all(inside) {
+            return Err(format!(
+                "a snake of length {} does not fit behind the head at ({}, {})",
+                len, self.head.0, self.head.1
+            ));
+        }
+        if !inside(&self.feed) || body.contains(&self.feed) {
+            return Err(format!(
+                "the feed at ({}, {}) must be inside the walls and off the snake",
+                self.feed.0, self.feed.1
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Width and height.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Size(pub u16, pub u16);
+
+/// Column and row. `Coord(0, 0)` is the top-left corner of the field.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Coord(pub u16, pub u16);
+
+impl Coord {
+    /// The next coordinate toward the direction.
+    /// Return None if it is out of the range of `u16`.
+    pub fn adjascent(&self, dir: &Direction) -> Option<Self> {
+        Some(match dir {
+            Direction::Up => Self(self.0, self.1.checked_sub(1)?),
+            Direction::Down => Self(self.0, self.1.checked_add(1)?),
+            Direction::Left => Self(self.0.checked_sub(1)?, self.1),
+            Direction::Right => Self(self.0.checked_add(1)?, self.1),
+        })
+    }
+
+    /// A random coordinate between `min` and `max`, both inclusive.
+    fn rand<R: Rng>(rng: &mut R, min: &Size, max: &Size) -> Self {
+        assert!(min.0 <= max.0 && min.1 <= max.1);
+        Self(
+            min.0 + (rng.gen::<u16>() % (1 + max.0 - min.0)),
+            min.1 + (rng.gen::<u16>() % (1 + max.1 - min.1)),
+        )
+    }
+}
+
+/// A snake and the directions given by its player.
+#[derive(Clone, Serialize, Deserialize)]
+struct Snake {
+    /// Body of snake.
+    ///  body[0] is the head of the snake.
+    ///  body[body.len() - 1] is the tail of the snake.
+    /// A ring buffer is used so that a normal step (push the head, pop the tail) does not allocate.
+    body: VecDeque<Coord>,
+    score: usize,
+    /// Direction the snake moved last.
+    dir: Direction,
+    /// Directions given by the player which are not applied yet.
+    /// One of them is applied at each step, so that quick key presses are not lost.
+    dir_queue: VecDeque<Direction>,
+    /// Lives left, including the current one.
+    lives: usize,
+    /// Steps left in which a crash does not count, after a respawn.
+    invulnerable: usize,
+    /// Steps left in which the tail stays, to grow by what was eaten.
+    growing: usize,
+    /// Feeds eaten in a row, each soon after the previous one.
+    streak: usize,
+    /// Steps since the snake ate last.
+    since_eaten: usize,
+    /// Steps left in which the controls are reversed.
+    reversed: usize,
+    /// Steps left in which the snake goes through its body.
+    invincible: usize,
+    /// Segments which lie on another segment, after the snake went through its body.
+    crossed: usize,
+}
+
+impl Snake {
+    fn new(body: &[Coord], dir: Direction) -> Self {
+        Self {
+            body: body.iter().copied().collect(),
+            score: 0,
+            dir,
+            dir_queue: VecDeque::new(),
+            lives: 1,
+            invulnerable: 0,
+            growing: 0,
+            streak: 0,
+            since_eaten: 0,
+            reversed: 0,
+            invincible: 0,
+            crossed: 0,
+        }
+    }
+}
+
+/// State of a game.
+/// The field includes the walls, so the snake can move in `1..w - 1` x `1..h - 1`.
+///
+/// There is one snake, and a second one can be added for two players.
+/// Methods without a player index are about the first snake.
+///
+/// A game can be saved with serde and loaded to go on from the same state.
+/// The steps which could be undone are not saved.
+#[derive(Serialize, Deserialize)]
+pub struct SnakeGameLogic {
+    field_size: Size,
+    snakes: Vec<Snake>,
+    /// Cells the bodies are on, to find collisions in constant time.
+    /// It always has the same cells as the bodies of `snakes`.
+    occupied: HashSet<Coord>,
+    feeds: Vec<Coord>,
+    /// Number of feeds kept on the field, counting a golden or poison feed which took the place of one.
+    #[serde(default)]
+    n_feeds: usize,
+    /// Eating it shrinks the snake.
+    poison: Option<Coord>,
+    /// Moves left until the poison feed disappears, if it took the place of a feed.
+    #[serde(default)]
+    poison_left: Option<usize>,
+    /// Bonus feed and the number of moves left until it disappears.
+    golden: Option<(Coord, usize)>,
+    /// If true, a poison feed is always on the field.
+    poison_enabled: bool,
+    /// Eating it reverses the controls for a while.
+    reverse: Option<Coord>,
+    /// If true, a reverse feed is always on the field.
+    reverse_enabled: bool,
+    /// Rare item which makes the snake invincible, and the number of moves left until it disappears.
+    star: Option<(Coord, usize)>,
+    /// If true, a star sometimes appears.
+    star_enabled: bool,
+    /// Item which slows the game down, and the number of moves left until it disappears.
+    slow: Option<(Coord, usize)>,
+    /// If true, the slow item sometimes appears.
+    slow_enabled: bool,
+    /// Steps left in which the game is slowed down.
+    slow_left: usize,
+    /// Chances of the kinds of a new feed.
+    feed_weights: FeedWeights,
+    /// If true, a new feed does not appear right in front of a head.
+    avoid_head: bool,
+    /// Every random choice of the game is made by this, so that a game can be reproduced from the seed.
+    #[serde(with = "rng_seed")]
+    rng: StdRng,
+    /// If true, the snake goes through the left or right wall and appears from the opposite one.
+    wrap_x: bool,
+    /// If true, the snake goes through the top or bottom wall and appears from the opposite one.
+    wrap_y: bool,
+    /// If true, nothing ends the game, and a move into something is ignored.
+    zen: bool,
+    /// Steps taken since the start of the game.
+    ticks: usize,
+    /// Steps at the start of the game in which a crash does not count.
+    grace: usize,
+    /// Size of the window around the head where new things appear, if the field has no walls.
+    #[serde(default)]
+    view: Option<Size>,
+    /// Blocks inside the field. The game is over if the head hits one of them.
+    obstacles: HashSet<Coord>,
+    /// Bombs on the field. The game is over if the head touches one of them.
+    bombs: HashSet<Coord>,
+    /// Pairs of portals. A head which enters one of them comes out of the other.
+    #[serde(default)]
+    portals: Vec<(Coord, Coord)>,
+    /// Number of steps between new bombs, if bombs appear.
+    bomb_interval: Option<usize>,
+    /// Steps until the next bomb appears.
+    bomb_left: usize,
+    /// Cell and direction of the head of the first snake at the start.
+    start: (Coord, Direction),
+    /// Length of the snakes at the start, which poison cannot make them shorter than.
+    initial_len: usize,
+    /// Number of steps the feeds stay on their cells, if they move when not eaten.
+    feed_ttl: Option<usize>,
+    /// Steps until the feeds move.
+    feed_lifetime: usize,
+    /// Number of steps between the moves of the feeds to next cells, if they drift.
+    feed_drift: Option<usize>,
+    /// Steps until the feeds drift.
+    drift_left: usize,
+    /// Lives each snake starts with.
+    lives: usize,
+    /// Segments a snake grows by for each one of `Food::growth`.
+    growth_per_food: usize,
+    /// Steps within which a feed has to be eaten after the previous one to raise the multiplier, if there are combos.
+    combo_window: Option<usize>,
+    /// States before the last steps, the newest at the back, to undo them.
+    #[serde(skip)]
+    history: VecDeque<Snapshot>,
+    /// Number of steps which can be undone.
+    history_len: usize,
+    /// Channels the events of the game are sent to.
+    #[serde(skip)]
+    observers: Vec<Sender<GameEvent>>,
+}
+
+/// What [`SnakeGameLogic::to_json`] writes.
+#[derive(Serialize)]
+struct Board<'a> {
+    size: Size,
+    snake: Vec<&'a Coord>,
+    direction: Direction,
+    feeds: &'a [Coord],
+    score: usize,
+}
+
+/// The random number generator is saved as a seed drawn from it,
+/// so a loaded game makes the same random choices every time it is loaded.
+mod rng_seed {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(rng: &StdRng, serializer: S) -> Result<S::Ok, S::Error> {
+        rng.clone().gen::<u64>().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<StdRng, D::Error> {
+        u64::deserialize(deserializer).map(StdRng::seed_from_u64)
+    }
+}
+
+/// What a step changes.
+#[derive(Clone)]
+struct Snapshot {
+    snakes: Vec<Snake>,
+    occupied: HashSet<Coord>,
+    feeds: Vec<Coord>,
+    poison: Option<Coord>,
+    poison_left: Option<usize>,
+    golden: Option<(Coord, usize)>,
+    reverse: Option<Coord>,
+    star: Option<(Coord, usize)>,
+    slow: Option<(Coord, usize)>,
+    slow_left: usize,
+    ticks: usize,
+    rng: StdRng,
+    feed_lifetime: usize,
+    drift_left: usize,
+    bombs: HashSet<Coord>,
+    bomb_left: usize,
+}
+
+impl SnakeGameLogic {
+    /// Create a game with `n_feeds` feeds on the field at the same time.
+    /// The same `seed` always makes the same game.
+    ///
+    /// # Panics
+    /// Panics if the width or height is less than [`MIN_FIELD_LEN`].
+    pub fn new(field_size: Size, n_feeds: usize, seed: u64) -> Self {
+        Self::with_initial_len(field_size, n_feeds, seed, DEFAULT_INITIAL_LEN)
+    }
+
+    /// Create a game like [`new`](Self::new), with a snake of `len` cells.
+    /// The snake lies straight on the 1st row inside the walls, heading right.
+    ///
+    /// # Panics
+    /// Panics if the field is too small, or `len` is 0 or more than [`max_initial_len`].
+    pub fn with_initial_len(field_size: Size, n_feeds: usize, seed: u64, len: usize) -> Self {
+        assert_field_size(field_size);
+        assert!(
+            len >= 1 && len <= max_initial_len(field_size.0),
+            "a snake of length {} does not fit in the width {}",
+            len,
+            field_size.0
+        );
+        Self::with_start(field_size, n_feeds, seed, len, Start::new(field_size, len))
+    }
+
+    /// Create a game like [`with_initial_len`](Self::with_initial_len), with the snake and the first feed at the start.
+    ///
+    /// # Panics
+    /// Panics if the field is too small, or the start does not pass [`Start::check`].
+    pub fn with_start(
+        field_size: Size,
+        n_feeds: usize,
+        seed: u64,
+        len: usize,
+        start: Start,
+    ) -> Self {
+        assert_field_size(field_size);
+        if let Err(msg) = start.check(field_size, len) {
+            panic!("{}", msg);
+        }
+        Self::build(
+            field_size,
+            n_feeds,
+            seed,
+            len,
+            (start.head, start.dir),
+            vec![start.feed],
+            HashSet::new(),
+        )
+    }
+
+    /// Create a game without walls, with a snake of `len` cells heading right and the first feed below it.
+    /// The field is as large as [`Coord`] allows, and the snakes go around at its ends, so they do not meet them in practice.
+    /// New things appear in the window of `view` around the head, which [`view`](Self::view) gives to draw.
+    ///
+    /// # Panics
+    /// Panics if `view` is smaller than [`MIN_FIELD_LEN`], or `len` is 0 or more than [`max_initial_len`] of its width.
+    pub fn without_walls(view: Size, n_feeds: usize, seed: u64, len: usize) -> Self {
+        assert_field_size(view);
+        assert!(
+            len >= 1 && len <= max_initial_len(view.0),
+            "a snake of length {} does not fit in the width {}",
+            len,
+            view.0
+        );
+        let head = Coord(u16::MAX / 2, u16::MAX / 2);
+        let feed = Coord(head.0, head.1 + view.1 / 4);
+        let field_size = Size(u16::MAX, u16::MAX);
+        let mut logic = Self::build(
+            field_size,
+            1,
+            seed,
+            len,
+            (head, Direction::Right),
+            vec![feed],
+            HashSet::new(),
+        );
+        logic.view = Some(view);
+        logic.n_feeds = n_feeds;
+        while logic.feeds.len() < n_feeds && logic.n_free_cells() > 0 {
+            logic.spawn_feed();
+        }
+        logic
+    }
+
+    /// Create a game on the field of the level, with a snake of `len` cells.
+    ///
+    /// # Panics
+    /// Panics if `len` is 0 or more than [`Level::max_initial_len`].
+    pub fn from_level(level: &Level, n_feeds: usize, seed: u64, len: usize) -> Self {
+        assert!(
+            len >= 1 && len <= level.max_initial_len(),
+            "a snake of length {} does not fit in the level",
+            len
+        );
+        let obstacles = level.obstacles().iter().copied().collect();
+        let feeds = level.feeds().to_vec();
+        let mut logic = Self::build(
+            level.size(),
+            n_feeds,
+            seed,
+            len,
+            level.start(),
+            feeds,
+            obstacles,
+        );
+        logic.portals = level.portals().to_vec();
+        logic
+    }
+
+    fn build(
+        field_size: Size,
+        n_feeds: usize,
+        seed: u64,
+        len: usize,
+        start: (Coord, Direction),
+        feeds: Vec<Coord>,
+        obstacles: HashSet<Coord>,
+    ) -> Self {
+        let mut logic = Self {
+            field_size,
+            snakes: Vec::new(),
+            occupied: HashSet::new(),
+            n_feeds: n_feeds.max(feeds.len()),
+            feeds,
+            poison: None,
+            poison_left: None,
+            golden: None,
+            poison_enabled: false,
+            reverse: None,
+            reverse_enabled: false,
+            star: None,
+            star_enabled: false,
+            slow: None,
+            slow_enabled: false,
+            slow_left: 0,
+            feed_weights: FeedWeights::default(),
+            avoid_head: false,
+            rng: StdRng::seed_from_u64(seed),
+            wrap_x: false,
+            wrap_y: false,
+            zen: false,
+            ticks: 0,
+            grace: 0,
+            view: None,
+            obstacles,
+            bombs: HashSet::new(),
+            portals: Vec::new(),
+            bomb_interval: None,
+            bomb_left: 0,
+            start,
+            initial_len: len,
+            feed_ttl: None,
+            feed_lifetime: 0,
+            feed_drift: None,
+            drift_left: 0,
+            lives: 1,
+            growth_per_food: 1,
+            combo_window: None,
+            history: VecDeque::new(),
+            history_len: 0,
+            observers: Vec::new(),
+        };
+        let snake = logic.start_snake(0);
+        logic.occupied.extend(&snake.body);
+        logic.snakes.push(snake);
+
+        while logic.feeds.len() < n_feeds && logic.n_free_cells() > 0 {
+            logic.spawn_feed();
+        }
+        logic
+    }
+
+    /// Add the snake of the second player on the bottom side, moving left.
+    /// It has to be called before putting anything other than feeds on the field.
+    ///
+    /// # Panics
+    /// Panics if there are two snakes already.
+    pub fn add_snake(&mut self) {
+        assert_eq!(self.snakes.len(), 1, "only two players are supported");
+        let mut snake = self.start_snake(1);
+        snake.lives = self.lives;
+        let body: Vec<Coord> = snake.body.iter().copied().collect();
+        self.occupied.extend(&body);
+        self.snakes.push(snake);
+        self.move_items_off(&body);
+    }
+
+    /// The snake of the player as it is at the start.
+    /// The first one is on the top side moving right, and the second one is on the bottom side moving left.
+    fn start_snake(&self, player: usize) -> Snake {
+        let Size(w, h) = self.field_size;
+        let len = self.initial_len as u16;
+        if player == 0 {
+            let (head, dir) = self.start;
+            let back = dir.opposite();
+            let body: Vec<Coord> = iter::successors(Some(head), |c| c.adjascent(&back))
+                .take(len as usize)
+                .collect();
+            Snake::new(&body, dir)
+        } else {
+            let body: Vec<Coord> = (0..len).map(|i| Coord(w - 2 - len + i, h - 3)).collect();
+            Snake::new(&body, Direction::Left)
+        }
+    }
+
+    /// Put what is on the cells somewhere else, as a snake has just been put on them.
+    /// A golden feed, a star, a slow item and bombs disappear instead.
+    fn move_items_off(&mut self, cells: &[Coord]) {
+        self.bombs.retain(|b| !cells.contains(b));
+        if self.golden.is_some_and(|(p, _)| cells.contains(&p)) {
+            self.golden = None;
+        }
+        if self.star.is_some_and(|(p, _)| cells.contains(&p)) {
+            self.star = None;
+        }
+        if self.slow.is_some_and(|(p, _)| cells.contains(&p)) {
+            self.slow = None;
+        }
+        let poisoned = self.poison.is_some_and(|p| cells.contains(&p));
+        if poisoned {
+            self.poison = None;
+            self.poison_left = None;
+        }
+        let reverse = self.reverse.is_some_and(|p| cells.contains(&p));
+        if reverse {
+            self.reverse = None;
+        }
+        let n_feeds = self.feeds.len();
+        self.feeds.retain(|f| !cells.contains(f));
+        while self.feeds.len() < n_feeds && self.n_free_cells() > 0 {
+            if let Some(feed) = self.random_free_cell() {
+                self.feeds.push(feed);
+            }
+        }
+        if poisoned {
+            self.poison = self.random_free_cell();
+        }
+        if reverse {
+            self.reverse = self.random_free_cell();
+        }
+    }
+
+    /// Give every snake `lives` lives.
+    /// A crash takes one of them and the snake starts again from where it did, keeping the score.
+    /// The game is over when the last one is lost.
+    ///
+    /// # Panics
+    /// Panics if `lives` is 0.
+    pub fn set_lives(&mut self, lives: usize) {
+        assert!(lives >= 1, "a snake needs at least one life");
+        self.lives = lives;
+        for snake in &mut self.snakes {
+            snake.lives = lives;
+        }
+    }
+
+    /// Number of lives each snake starts with.
+    pub fn lives(&self) -> usize {
+        self.lives
+    }
+
+    /// Lives left to the first snake, including the current one.
+    pub fn lives_left(&self) -> usize {
+        self.lives_left_of(0)
+    }
+
+    pub fn lives_left_of(&self, player: usize) -> usize {
+        self.snakes[player].lives
+    }
+
+    /// Return true if a crash of the snake does not count now, right after a respawn.
+    pub fn is_invulnerable_of(&self, player: usize) -> bool {
+        self.snakes[player].invulnerable > 0
+    }
+
+    /// Number of snakes, which is the number of players.
+    pub fn n_snakes(&self) -> usize {
+        self.snakes.len()
+    }
+
+    /// Let the snakes go through every wall, or none of them.
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap_x = wrap;
+        self.wrap_y = wrap;
+    }
+
+    /// Let the snakes go through the left and right walls, independently of the top and bottom ones.
+    pub fn set_wrap_x(&mut self, wrap: bool) {
+        self.wrap_x = wrap;
+    }
+
+    /// Let the snakes go through the top and bottom walls, independently of the left and right ones.
+    pub fn set_wrap_y(&mut self, wrap: bool) {
+        self.wrap_y = wrap;
+    }
+
+    /// Play without dying: the snakes go through the walls, and a move which would crash is ignored.
+    /// The snake stays where it is until the player turns it.
+    pub fn set_zen(&mut self, zen: bool) {
+        self.zen = zen;
+        if zen {
+            self.set_wrap(true);
+        }
+    }
+
+    /// Let a crash not count in the first `steps` steps of the game, so that a key pressed by mistake does not end it at once.
+    /// The snake stays where it is until the player turns it, as after a respawn.
+    pub fn set_grace(&mut self, steps: usize) {
+        self.grace = steps;
+    }
+
+    /// Number of steps taken since the start of the game.
+    pub fn ticks(&self) -> usize {
+        self.ticks
+    }
+
+    /// Steps left in which a crash does not count, or 0 after the grace period.
+    pub fn grace_left(&self) -> usize {
+        self.grace.saturating_sub(self.ticks)
+    }
+
+    /// Keep a poison feed on the field, which appears again when eaten, or remove it.
+    pub fn set_poison(&mut self, poison: bool) {
+        self.poison_enabled = poison;
+        self.poison = None;
+        self.poison_left = None;
+        if poison {
+            self.poison = self.random_free_cell();
+        }
+    }
+
+    /// Keep a reverse feed on the field, which swaps up with down and left with right
+    /// for the snake which eats it for a while, or remove it.
+    /// It appears again when eaten.
+    pub fn set_reverse(&mut self, reverse: bool) {
+        self.reverse_enabled = reverse;
+        self.reverse = None;
+        if reverse {
+            self.reverse = self.random_free_cell();
+        }
+    }
+
+    /// Steps left in which the controls of the snake are reversed, or 0 if they are not.
+    pub fn reversed_of(&self, player: usize) -> usize {
+        self.snakes[player].reversed
+    }
+
+    /// Let a star appear rarely when a feed is eaten, or remove it.
+    /// The snake which eats it goes through its own body for a while, but not through the walls.
+    pub fn set_star(&mut self, star: bool) {
+        self.star_enabled = star;
+        if !star {
+            self.star = None;
+        }
+    }
+
+    /// Steps left in which the snake of the player goes through its body, or 0 if it does not.
+    pub fn invincible_of(&self, player: usize) -> usize {
+        self.snakes[player].invincible
+    }
+
+    /// Let an item which slows the game down appear sometimes when a feed is eaten, or remove it.
+    /// The logic does not keep time, so the front-end makes the steps longer while [`slow_left`](Self::slow_left) is not 0.
+    pub fn set_slow(&mut self, slow: bool) {
+        self.slow_enabled = slow;
+        if !slow {
+            self.slow = None;
+        }
+    }
+
+    /// Steps left in which the game is slowed down, or 0 if it is not.
+    pub fn slow_left(&self) -> usize {
+        self.slow_left
+    }
+
+    /// Let a new feed be a bonus one sometimes.
+    pub fn set_golden(&mut self, golden: bool) {
+        // 15% of the new feeds are golden.
+        const GOLDEN_WEIGHT: u32 = 15;
+
+        self.feed_weights.golden = if golden { GOLDEN_WEIGHT } else { 0 };
+        if !golden {
+            self.golden = None;
+        }
+    }
+
+    /// Make a snake grow by `growth` segments for a feed, one at each of the following steps.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `growth` is 0.
+    pub fn set_growth_per_food(&mut self, growth: usize) {
+        assert!(growth >= 1, "a feed must make the snake grow");
+        self.growth_per_food = growth;
+    }
+
+    /// Multiply the points of a feed by the number of feeds eaten in a row,
+    /// each within `window` steps after the previous one, up to [`MAX_COMBO`].
+    /// There are no combos if `None`.
+    pub fn set_combo_window(&mut self, window: Option<usize>) {
+        self.combo_window = window;
+    }
+
+    /// The multiplier of the points of the last feed, while the streak lasts.
+    pub fn combo(&self) -> usize {
+        self.combo_of(0)
+    }
+
+    pub fn combo_of(&self, player: usize) -> usize {
+        self.snakes[player].streak.clamp(1, MAX_COMBO)
+    }
+
+    /// Keep new feeds off the cells the heads move to next, so that the snakes do not get them for nothing.
+    /// A feed still goes there if there is no other free cell.
+    pub fn set_avoid_head(&mut self, avoid: bool) {
+        self.avoid_head = avoid;
+    }
+
+    /// Choose the kinds of new feeds by the weights.
+    ///
+    /// # Panics
+    ///
+    /// Panics if every weight is 0.
+    pub fn set_feed_weights(&mut self, weights: FeedWeights) {
+        assert!(
+            weights.feed > 0 || weights.golden > 0 || weights.poison > 0,
+            "some weight must be positive"
+        );
+        self.feed_weights = weights;
+        if weights.golden == 0 {
+            self.golden = None;
+        }
+    }
+
+    /// Make the feeds move to other cells when none of them is eaten in `ttl` steps,
+    /// or stay until eaten if `None`.
+    ///
+    /// # Panics
+    /// Panics if `ttl` is `Some(0)`.
+    pub fn set_feed_ttl(&mut self, ttl: Option<usize>) {
+        assert_ne!(ttl, Some(0), "the feeds have to stay for at least a step");
+        self.feed_ttl = ttl;
+        self.feed_lifetime = ttl.unwrap_or(0);
+    }
+
+    /// Make the feeds drift to a free cell next to them every `interval` steps,
+    /// or stay still if `None`.
+    ///
+    /// # Panics
+    /// Panics if `interval` is `Some(0)`.
+    pub fn set_feed_drift(&mut self, interval: Option<usize>) {
+        assert_ne!(
+            interval,
+            Some(0),
+            "the feeds have to stay for at least a step"
+        );
+        self.feed_drift = interval;
+        self.drift_left = interval.unwrap_or(0);
+    }
+
+    /// Put a bomb on a free cell every `interval` steps, up to [`MAX_BOMBS`], or no bombs if `None`.
+    /// A bomb does not appear right in front of a head.
+    ///
+    /// # Panics
+    /// Panics if `interval` is `Some(0)`.
+    pub fn set_bomb_interval(&mut self, interval: Option<usize>) {
+        assert_ne!(interval, Some(0), "bombs have to be at least a step apart");
+        self.bomb_interval = interval;
+        self.bomb_left = interval.unwrap_or(0);
+    }
+
+    /// Steps left until the feeds move, and the number of steps they stay for.
+    pub fn feed_lifetime(&self) -> Option<(usize, usize)> {
+        self.feed_ttl.map(|ttl| (self.feed_lifetime, ttl))
+    }
+
+    /// Put up to `n` obstacles on random free cells.
+    /// The lines the snakes start to move along are kept clear so that the game is not over right after the start.
+    pub fn place_obstacles(&mut self, n: usize) {
+        let lanes: Vec<(Coord, Direction)> =
+            self.snakes.iter().map(|s| (s.body[0], s.dir)).collect();
+        let in_lane = |c: &Coord| {
+            lanes.iter().any(|(head, dir)| match dir {
+                Direction::Left | Direction::Right => c.1 == head.1,
+                Direction::Up | Direction::Down => c.0 == head.0,
+            })
+        };
+        let free: Vec<Coord> = (self.spawn_cells())
+            .filter(|c| !in_lane(c) && !self.is_occupied(c))
+            .collect();
+
+        self.obstacles
+            .extend(free.choose_multiple(&mut self.rng, n).copied());
+    }
+
+    /// Put up to `n` pairs of portals on random free cells, off the lines the snakes start to move along.
+    pub fn place_portals(&mut self, n: usize) {
+        let lanes: Vec<(Coord, Direction)> =
+            self.snakes.iter().map(|s| (s.body[0], s.dir)).collect();
+        let in_lane = |c: &Coord| {
+            lanes.iter().any(|(head, dir)| match dir {
+                Direction::Left | Direction::Right => c.1 == head.1,
+                Direction::Up | Direction::Down => c.0 == head.0,
+            })
+        };
+        let free: Vec<Coord> = (self.spawn_cells())
+            .filter(|c| !in_lane(c) && !self.is_occupied(c))
+            .collect();
+
+        let cells: Vec<Coord> = free
+            .choose_multiple(&mut self.rng, n * 2)
+            .copied()
+            .collect();
+        self.portals
+            .extend(cells.chunks_exact(2).map(|pair| (pair[0], pair[1])));
+    }
+
+    /// Pairs of portals, which take a head from one to the other.
+    pub fn portals(&self) -> &[(Coord, Coord)] {
+        &self.portals
+    }
+
+    /// The portal paired with the one on the cell, if there is a portal on it.
+    fn portal_exit(&self, c: &Coord) -> Option<Coord> {
+        self.portals.iter().find_map(|&(a, b)| {
+            if a == *c {
+                Some(b)
+            } else if b == *c {
+                Some(a)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Change the direction the snake moves.
+    /// Directions given between two steps are applied one by one in the following steps.
+    /// A direction opposite from the one the snake moves at that time is ignored.
+    pub fn set_dir(&mut self, dir: Direction) {
+        self.set_dir_of(0, dir);
+    }
+
+    /// Change the direction the snake of the player moves, like [`set_dir`](Self::set_dir).
+    pub fn set_dir_of(&mut self, player: usize, dir: Direction) {
+        const QUEUE_LEN: usize = 3;
+
+        let queue = &mut self.snakes[player].dir_queue;
+        if queue.len() < QUEUE_LEN && queue.back() != Some(&dir) {
+            queue.push_back(dir);
+        }
+    }
+
+    pub fn get_dir(&self) -> &Direction {
+        self.get_dir_of(0)
+    }
+
+    pub fn get_dir_of(&self, player: usize) -> &Direction {
+        &self.snakes[player].dir
+    }
+
+    /// The direction the snake moves, as [`get_dir`](Self::get_dir) but by value.
+    pub fn direction(&self) -> Direction {
+        *self.get_dir()
+    }
+
+    pub fn field_size(&self) -> Size {
+        self.field_size
+    }
+
+    /// Top-left cell and size of the window around the head of the first snake, if the field has no walls.
+    /// Only the window is drawn, and new things appear in it.
+    pub fn view(&self) -> Option<(Coord, Size)> {
+        let size @ Size(w, h) = self.view?;
+        let Coord(x, y) = self.snakes[0].body[0];
+        // The window stays off the ends of the field.
+        let left = x.saturating_sub(w / 2).clamp(1, u16::MAX - 1 - w);
+        let top = y.saturating_sub(h / 2).clamp(1, u16::MAX - 1 - h);
+        Some((Coord(left, top), size))
+    }
+
+    /// Cells where things can be put: the inner field, or the window if there are no walls.
+    fn spawn_cells(&self) -> impl Iterator<Item = Coord> {
+        let (Coord(left, top), Size(w, h)) = self.view().unwrap_or((
+            Coord(1, 1),
+            Size(self.field_size.0 - 2, self.field_size.1 - 2),
+        ));
+        (top..top + h).flat_map(move |y| (left..left + w).map(move |x| Coord(x, y)))
+    }
+
+    /// Iterate the body from the head to the tail.
+    pub fn body(&self) -> impl Iterator<Item = &Coord> {
+        self.body_of(0)
+    }
+
+    pub fn body_of(&self, player: usize) -> impl Iterator<Item = &Coord> {
+        self.snakes[player].body.iter()
+    }
+
+    pub fn feeds(&self) -> &[Coord] {
+        &self.feeds
+    }
+
+    /// The first feed, for a game with only one.
+    /// Return None if every feed has been eaten and the field is full.
+    pub fn feed(&self) -> Option<Coord> {
+        self.feeds.first().copied()
+    }
+
+    pub fn poison(&self) -> Option<Coord> {
+        self.poison
+    }
+
+    pub fn golden(&self) -> Option<Coord> {
+        self.golden.map(|(p, _)| p)
+    }
+
+    pub fn reverse(&self) -> Option<Coord> {
+        self.reverse
+    }
+
+    pub fn star(&self) -> Option<Coord> {
+        self.star.map(|(p, _)| p)
+    }
+
+    pub fn slow(&self) -> Option<Coord> {
+        self.slow.map(|(p, _)| p)
+    }
+
+    pub fn obstacles(&self) -> impl Iterator<Item = &Coord> {
+        self.obstacles.iter()
+    }
+
+    pub fn bombs(&self) -> impl Iterator<Item = &Coord> {
+        self.bombs.iter()
+    }
+
+    /// The board as JSON, for tools outside the game.
+    /// Cells are `[x, y]` arrays and the snake is listed from the head.
+    ///
+    /// ```json
+    /// {"size":[20,20],"snake":[[4,2],[3,2],[2,2]],"direction":"Right","feeds":[[9,14]],"score":0}
+    /// ```
+    pub fn to_json(&self) -> String {
+        let board = Board {
+            size: self.field_size,
+            snake: self.body().collect(),
+            direction: *self.get_dir(),
+            feeds: &self.feeds,
+            score: self.score(),
+        };
+        serde_json::to_string(&board).expect("a board is always serializable")
+    }
+
+    fn is_inner_field(&self, c: &Coord) -> bool {
+        let Size(w, h) = self.field_size;
+        (1..w - 1).contains(&c.0) && (1..h - 1).contains(&c.1)
+    }
+
+    /// The cell on the opposite side of the inner field, where the head goes out toward the direction.
+    fn wrap_around(&self, head: &Coord, dir: &Direction) -> Coord {
+        let Size(w, h) = self.field_size;
+        match dir {
+            Direction::Up => Coord(head.0, h - 2),
+            Direction::Down => Coord(head.0, 1),
+            Direction::Left => Coord(w - 2, head.1),
+            Direction::Right => Coord(1, head.1),
+        }
+    }
+
+    /// Return true if the snake goes through the wall toward the direction.
+    fn wraps(&self, dir: &Direction) -> bool {
+        if self.view.is_some() {
+            return true;
+        }
+        match dir {
+            Direction::Left | Direction::Right => self.wrap_x,
+            Direction::Up | Direction::Down => self.wrap_y,
+        }
+    }
+
+    /// The cell the head at `c` moves to toward the direction.
+    /// Return None if it hits a wall.
+    fn next_cell(&self, c: &Coord, dir: &Direction) -> Option<Coord> {
+        match c.adjascent(dir) {
+            Some(adj) if self.is_inner_field(&adj) => Some(adj),
+            _ if self.wraps(dir) => Some(self.wrap_around(c, dir)),
+            _ => None,
+        }
+    }
+
+    /// Return true if something is on the cell.
+    fn is_occupied(&self, c: &Coord) -> bool {
+        self.obstacles.contains(c)
+            || self.bombs.contains(c)
+            || self.portal_exit(c).is_some()
+            || self.feeds.contains(c)
+            || self.poison.as_ref() == Some(c)
+            || self.golden.map(|(p, _)| p) == Some(*c)
+            || self.reverse.as_ref() == Some(c)
+            || self.star.map(|(p, _)| p) == Some(*c)
+            || self.slow.map(|(p, _)| p) == Some(*c)
+            || self.occupied.contains(c)
+    }
+
+    fn push_head(&mut self, player: usize, c: Coord) {
+        self.snakes[player].body.push_front(c);
+        self.occupied.insert(c);
+    }
+
+    /// Move the head to the cell, and the tail after it unless the snake is growing.
+    fn crawl(&mut self, player: usize, head: Coord) {
+        let snake = &mut self.snakes[player];
+        let grows = snake.growing > 0;
+        if grows {
+            snake.growing -= 1;
+        } else {
+            self.pop_tail(player);
+        }
+        self.push_head(player, head);
+        self.emit(GameEvent::Moved { player, head });
+        if grows {
+            let len = self.snakes[player].body.len();
+            self.emit(GameEvent::Grew { player, len });
+        }
+    }
+
+    fn pop_tail(&mut self, player: usize) {
+        let snake = &mut self.snakes[player];
+        if let Some(c) = snake.body.pop_back() {
+            // The cell stays taken if another segment lies on it.
+            if snake.crossed > 0 && snake.body.contains(&c) {
+                snake.crossed -= 1;
+            } else {
+                self.occupied.remove(&c);
+            }
+        }
+    }
+
+    /// Number of cells of the inner field where nothing is on.
+    fn n_free_cells(&self) -> usize {
+        if self.view.is_some() {
+            return (self.spawn_cells())
+                .filter(|c| !self.is_occupied(c))
+                .count();
+        }
+        let Size(w, h) = self.field_size;
+        let n_inner = (w as usize - 2) * (h as usize - 2);
+        n_inner
+            - self.occupied.len()
+            - self.obstacles.len()
+            - self.bombs.len()
+            - self.portals.len() * 2
+            - self.feeds.len()
+            - self.poison.iter().count()
+            - self.golden.iter().count()
+            - self.reverse.iter().count()
+            - self.star.iter().count()
+            - self.slow.iter().count()
+    }
+
+    /// Pick a random cell of the inner field where nothing is on.
+    /// Return None if there is no such cell.
+    fn random_free_cell(&mut self) -> Option<Coord> {
+        if self.view.is_some() {
+            let free: Vec<Coord> = (self.spawn_cells())
+                .filter(|c| !self.is_occupied(c))
+                .collect();
+            return free.choose(&mut self.rng).copied();
+        }
+        if self.n_free_cells() == 0 {
+            return None;
+        }
+        let max = Size(self.field_size.0 - 2, self.field_size.1 - 2);
+        loop {
+            let candidate = Coord::rand(&mut self.rng, &Size(1, 1), &max);
+            if !self.is_occupied(&candidate) {
+                return Some(candidate);
+            }
+        }
+    }
+
+    /// Put a new feed of the kind chosen by the weights on a random cell that is not occupied.
+    /// A star or a slow item may come with it.
+    /// Nothing happens if the field is full.
+    fn spawn_feed(&mut self) {
+        // Moves a golden or poison feed stays for.
+        const FEED_LIFETIME: usize = 40;
+        // A star comes with one of this many new feeds.
+        const STAR_RARITY: u32 = 20;
+        const STAR_LIFETIME: usize = 30;
+        const SLOW_RARITY: u32 = 10;
+        const SLOW_LIFETIME: usize = 40;
+
+        if let Some(feed) = self.random_feed_cell() {
+            match self.feed_kind() {
+                FeedKind::Golden if self.golden.is_none() => {
+                    self.golden = Some((feed, FEED_LIFETIME));
+                }
+                FeedKind::Poison if self.poison.is_none() => {
+                    self.poison = Some(feed);
+                    self.poison_left = Some(FEED_LIFETIME);
+                }
+                _ => self.feeds.push(feed),
+            }
+        }
+        if self.star_enabled && self.star.is_none() && self.rng.gen_ratio(1, STAR_RARITY) {
+            self.star = self.random_free_cell().map(|p| (p, STAR_LIFETIME));
+        }
+        if self.slow_enabled && self.slow.is_none() && self.rng.gen_ratio(1, SLOW_RARITY) {
+            self.slow = self.random_free_cell().map(|p| (p, SLOW_LIFETIME));
+        }
+    }
+
+    /// Choose the kind of a new feed by the weights.
+    fn feed_kind(&mut self) -> FeedKind {
+        let weights = FeedKind::ALL.map(|kind| self.feed_weights.of(kind));
+        // Only normal feeds appear by default, and then no random choice is made.
+        if weights[1..].iter().all(|w| *w == 0) {
+            return FeedKind::Normal;
+        }
+        match WeightedIndex::new(weights) {
+            Ok(dist) => FeedKind::ALL[dist.sample(&mut self.rng)],
+            Err(_) => FeedKind::Normal,
+        }
+    }
+
+    /// Number of feeds on the field, counting a golden or poison feed which took the place of one.
+    fn n_placed_feeds(&self) -> usize {
+        self.feeds.len()
+            + usize::from(self.golden.is_some())
+            + usize::from(self.poison_left.is_some())
+    }
+
+    /// Pick a free cell for a new feed, avoiding the cells in front of the heads if told to.
+    fn random_feed_cell(&mut self) -> Option<Coord> {
+        if !self.avoid_head {
+            return self.random_free_cell();
+        }
+        self.random_cell_off_heads()
+            .or_else(|| self.random_free_cell())
+    }
+
+    /// Pick a free cell which no head moves to next.
+    /// Return None if there is no such cell.
+    fn random_cell_off_heads(&mut self) -> Option<Coord> {
+        let ahead: HashSet<Coord> = (self.snakes.iter())
+            .filter_map(|s| self.next_cell(&s.body[0], &s.dir))
+            .filter(|c| !self.is_occupied(c))
+            .collect();
+        if self.n_free_cells() <= ahead.len() {
+            return None;
+        }
+        loop {
+            let cell = self.random_free_cell()?;
+            if !ahead.contains(&cell) {
+                return Some(cell);
+            }
+        }
+    }
+
+    /// The food on the cell, if any.
+    fn food_at(&self, c: &Coord) -> Option<Food> {
+        if self.feeds.contains(c) {
+            Some(Food::Feed)
+        } else if self.golden.map(|(p, _)| p) == Some(*c) {
+            Some(Food::Golden)
+        } else if self.poison == Some(*c) {
+            Some(Food::Poison)
+        } else if self.reverse == Some(*c) {
+            Some(Food::Reverse)
+        } else if self.star.map(|(p, _)| p) == Some(*c) {
+            Some(Food::Star)
+        } else if self.slow.map(|(p, _)| p) == Some(*c) {
+            Some(Food::Slow)
+        } else {
+            None
+        }
+    }
+
+    /// Move every feed to another free cell.
+    /// A feed stays if there is no free cell.
+    fn relocate_feeds(&mut self) {
+        for i in 0..self.feeds.len() {
+            // The old cell is still taken, so the feed does not stay on it.
+            if let Some(feed) = self.random_free_cell() {
+                self.feeds[i] = feed;
+            }
+        }
+    }
+
+    /// Move every feed to a random free cell next to it.
+    /// A feed stays if there is no such cell.
+    fn drift_feeds(&mut self) {
+        const DIRECTIONS: [Direction; 4] = [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ];
+
+        for i in 0..self.feeds.len() {
+            let cells: Vec<Coord> = DIRECTIONS
+                .iter()
+                .filter_map(|d| self.feeds[i].adjascent(d))
+                .filter(|c| self.is_inner_field(c) && !self.is_occupied(c))
+                .collect();
+            if let Some(c) = cells.choose(&mut self.rng) {
+                self.feeds[i] = *c;
+            }
+        }
+    }
+
+    pub fn score(&self) -> usize {
+        self.score_of(0)
+    }
+
+    pub fn score_of(&self, player: usize) -> usize {
+        self.snakes[player].score
+    }
+
+    /// Start with the score, such as the one made in the previous level.
+    pub fn set_score(&mut self, score: usize) {
+        self.snakes[0].score = score;
+    }
+
+    /// Move head toward the direction.
+    /// Nothing else advances the game, so it can be simulated without any front-end.
+    ///
+    /// With two snakes, both move and the result of the first one is returned.
+    /// Use [`step_each`](Self::step_each) to know both.
+    pub fn step(&mut self) -> MoveResult {
+        self.start_step();
+        let result = self.move_snake(0);
+        for player in 1..self.snakes.len() {
+            self.move_snake(player);
+        }
+        self.end_step();
+        self.finish(result)
+    }
+
+    /// Move every snake, and return what happened to each of them.
+    /// The snakes move in the order of the players, so the second snake can run into where the first one has just moved.
+    pub fn step_each(&mut self) -> Vec<MoveResult> {
+        self.start_step();
+        let results: Vec<MoveResult> = (0..self.snakes.len())
+            .map(|player| self.move_snake(player))
+            .collect();
+        self.end_step();
+        results.into_iter().map(|r| self.finish(r)).collect()
+    }
+
+    /// Keep the last `len` states to undo steps with [`step_back`](Self::step_back).
+    /// Every step copies the whole state, so it is meant for practice and debugging.
+    pub fn set_history_len(&mut self, len: usize) {
+        self.history_len = len;
+        while self.history.len() > len {
+            self.history.pop_front();
+        }
+    }
+
+    /// Receive the events of the game from now on, such as moves, foods eaten and the end of the game.
+    /// Events are not taken back by [`step_back`](Self::step_back), and are not saved with the game.
+    pub fn subscribe(&mut self) -> Receiver<GameEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.observers.push(tx);
+        rx
+    }
+
+    /// Undo the last step, including the random choices made in it.
+    /// Return false if there is no step to undo.
+    pub fn step_back(&mut self) -> bool {
+        let Some(snapshot) = self.history.pop_back() else {
+            return false;
+        };
+        self.snakes = snapshot.snakes;
+        self.occupied = snapshot.occupied;
+        self.feeds = snapshot.feeds;
+        self.poison = snapshot.poison;
+        self.poison_left = snapshot.poison_left;
+        self.golden = snapshot.golden;
+        self.reverse = snapshot.reverse;
+        self.star = snapshot.star;
+        self.slow = snapshot.slow;
+        self.slow_left = snapshot.slow_left;
+        self.ticks = snapshot.ticks;
+        self.rng = snapshot.rng;
+        self.feed_lifetime = snapshot.feed_lifetime;
+        self.drift_left = snapshot.drift_left;
+        self.bombs = snapshot.bombs;
+        self.bomb_left = snapshot.bomb_left;
+        true
+    }
+
+    fn save_history(&mut self) {
+        if self.history_len == 0 {
+            return;
+        }
+        if self.history.len() == self.history_len {
+            self.history.pop_front();
+        }
+        self.history.push_back(Snapshot {
+            snakes: self.snakes.clone(),
+            occupied: self.occupied.clone(),
+            feeds: self.feeds.clone(),
+            poison: self.poison,
+            poison_left: self.poison_left,
+            golden: self.golden,
+            reverse: self.reverse,
+            star: self.star,
+            slow: self.slow,
+            slow_left: self.slow_left,
+            ticks: self.ticks,
+            rng: self.rng.clone(),
+            feed_lifetime: self.feed_lifetime,
+            drift_left: self.drift_left,
+            bombs: self.bombs.clone(),
+            bomb_left: self.bomb_left,
+        });
+    }
+
+    /// Move a snake without checking the end of the game.
+    /// A crash takes a life if the snake has another one.
+    fn move_snake(&mut self, player: usize) -> MoveResult {
+        let result = self.advance(player);
+        let snake = &mut self.snakes[player];
+        let invulnerable = snake.invulnerable > 0;
+        snake.invulnerable = snake.invulnerable.saturating_sub(1);
+        if !result.is_over() {
+            return result;
+        }
+        // The grace period includes the step taking the last of it.
+        if invulnerable || self.zen || self.ticks <= self.grace {
+            // The snake waits for the player to turn it.
+            return MoveResult::Alive;
+        }
+        if snake.lives > 1 && self.respawn(player) {
+            return MoveResult::LostLife;
+        }
+        result
+    }
+
+    /// Put the snake back at its start with one life less.
+    /// Return false if the other snake is in the way, and the snake cannot start again.
+    fn respawn(&mut self, player: usize) -> bool {
+        // The snake does not crash soon again while the player gets ready.
+        const INVULNERABLE_STEPS: usize = 10;
+
+        let mut snake = self.start_snake(player);
+        let others = (self.snakes.iter().enumerate()).filter(|(i, _)| *i != player);
+        if others
+            .flat_map(|(_, s)| &s.body)
+            .any(|c| snake.body.contains(c))
+        {
+            return false;
+        }
+
+        let old = &self.snakes[player];
+        snake.score = old.score;
+        snake.lives = old.lives - 1;
+        snake.invulnerable = INVULNERABLE_STEPS;
+        for c in &old.body {
+            self.occupied.remove(c);
+        }
+        let body: Vec<Coord> = snake.body.iter().copied().collect();
+        self.occupied.extend(&body);
+        self.snakes[player] = snake;
+        self.move_items_off(&body);
+        true
+    }
+
+    /// Move a snake over whatever is in front of it.
+    fn advance(&mut self, player: usize) -> MoveResult {
+        let snake = &mut self.snakes[player];
+
+        // Update direction with the first one which turns the snake.
+        // The controls are swapped while reversed.
+        while let Some(dir) = snake.dir_queue.pop_front() {
+            let dir = if snake.reversed > 0 {
+                dir.opposite()
+            } else {
+                dir
+            };
+            if dir != snake.dir && dir != snake.dir.opposite() {
+                snake.dir = dir;
+                break;
+            }
+        }
+        snake.reversed = snake.reversed.saturating_sub(1);
+        snake.invincible = snake.invincible.saturating_sub(1);
+
+        let head = snake.body[0];
+        let dir = snake.dir;
+        let Some(mut adj) = self.next_cell(&head, &dir) else {
+            return MoveResult::HitWall;
+        };
+        // A portal takes the head out of the other one, to the cell after it, which can be another portal.
+        // Only one cell leads into each portal, so a chain of them cannot go round in a loop.
+        while let Some(exit) = self.portal_exit(&adj) {
+            let Some(next) = self.next_cell(&exit, &dir) else {
+                return MoveResult::HitWall;
+            };
+            adj = next;
+        }
+
+        if self.obstacles.contains(&adj) {
+            return MoveResult::HitObstacle;
+        }
+        if self.bombs.contains(&adj) {
+            return MoveResult::HitBomb;
+        }
+
+        // Collidge with body.
+        // Nothing to eat is on the body, so the tail moves away at this step and does not count unless the snake is growing.
+        let snake = &mut self.snakes[player];
+        let body = &snake.body;
+        if self.occupied.contains(&adj) && (body.back() != Some(&adj) || snake.growing > 0) {
+            if !body.contains(&adj) {
+                return MoveResult::HitSnake;
+            }
+            if snake.invincible == 0 {
+                return MoveResult::HitSelf;
+            }
+            // The head goes over the body.
+            snake.crossed += 1;
+        }
+
+        // The streak breaks when nothing is eaten in time.
+        let snake = &mut self.snakes[player];
+        snake.since_eaten += 1;
+        if self.combo_window.is_some_and(|w| snake.since_eaten > w) {
+            snake.streak = 0;
+        }
+
+        // Move or Grow
+        let Some(food) = self.food_at(&adj) else {
+            self.crawl(player, adj);
+            return MoveResult::Alive;
+        };
+        // The snake must not get shorter than the initial length.
+        let growth = food.growth();
+        if (self.snakes[player].body.len() as isize + growth) < self.initial_len as isize {
+            return MoveResult::Poisoned;
+        }
+        match food {
+            Food::Feed => {
+                let i = self.feeds.iter().position(|f| *f == adj).unwrap();
+                self.feeds.swap_remove(i);
+            }
+            Food::Golden => self.golden = None,
+            Food::Poison => {
+                self.poison = None;
+                self.poison_left = None;
+            }
+            Food::Reverse => {
+                // Steps the controls stay reversed for.
+                const REVERSE_STEPS: usize = 30;
+
+                self.reverse = None;
+                self.snakes[player].reversed = REVERSE_STEPS;
+            }
+            Food::Star => {
+                // Steps the snake stays invincible for.
+                const INVINCIBLE_STEPS: usize = 50;
+
+                self.star = None;
+                self.snakes[player].invincible = INVINCIBLE_STEPS;
+            }
+            Food::Slow => {
+                // Steps the game stays slow for.
+                const SLOW_STEPS: usize = 40;
+
+                self.slow = None;
+                self.slow_left = SLOW_STEPS;
+            }
+        }
+        // The tail stays for a step per segment to grow, and moves one more cell to shrink.
+        if growth > 0 {
+            self.snakes[player].growing += growth as usize * self.growth_per_food;
+        }
+        for _ in growth..0 {
+            self.pop_tail(player);
+        }
+        self.crawl(player, adj);
+        let mut points = food.points();
+        let snake = &mut self.snakes[player];
+        if points > 0 && self.combo_window.is_some() {
+            snake.streak += 1;
+            snake.since_eaten = 0;
+            points *= snake.streak.min(MAX_COMBO) as isize;
+        }
+        snake.score = snake.score.saturating_add_signed(points);
+        match food {
+            Food::Feed => {
+                self.spawn_feed();
+                self.feed_lifetime = self.feed_ttl.unwrap_or(0);
+            }
+            Food::Poison if self.poison_enabled => self.poison = self.random_free_cell(),
+            Food::Reverse if self.reverse_enabled => self.reverse = self.random_free_cell(),
+            _ => {}
+        }
+        self.emit(GameEvent::Ate { player, food });
+        MoveResult::Ate(food)
+    }
+
+    /// Get ready to move the snakes.
+    fn start_step(&mut self) {
+        self.save_history();
+        self.ticks += 1;
+        // Each move made in slow motion uses up one of its steps.
+        self.slow_left = self.slow_left.saturating_sub(1);
+    }
+
+    /// Advance what does not belong to a snake.
+    fn end_step(&mut self) {
+        // The feeds move when they are not eaten in time.
+        if self.feed_ttl.is_some() {
+            self.feed_lifetime -= 1;
+            if self.feed_lifetime == 0 {
+                self.relocate_feeds();
+                self.feed_lifetime = self.feed_ttl.unwrap_or(0);
+            }
+        }
+
+        if let Some(interval) = self.feed_drift {
+            self.drift_left -= 1;
+            if self.drift_left == 0 {
+                self.drift_feeds();
+                self.drift_left = interval;
+            }
+        }
+
+        if let Some(interval) = self.bomb_interval {
+            self.bomb_left -= 1;
+            if self.bomb_left == 0 {
+                if self.bombs.len() < MAX_BOMBS {
+                    if let Some(cell) = self.random_cell_off_heads() {
+                        self.bombs.insert(cell);
+                    }
+                }
+                self.bomb_left = interval;
+            }
+        }
+
+        // The bonus feed, the star and the slow item disappear when they are not eaten in time.
+        if let Some((_, lifetime)) = &mut self.golden {
+            *lifetime -= 1;
+            if *lifetime == 0 {
+                self.golden = None;
+            }
+        }
+        if let Some(lifetime) = &mut self.poison_left {
+            *lifetime -= 1;
+            if *lifetime == 0 {
+                self.poison = None;
+                self.poison_left = None;
+            }
+        }
+        if let Some((_, lifetime)) = &mut self.star {
+            *lifetime -= 1;
+            if *lifetime == 0 {
+                self.star = None;
+            }
+        }
+        if let Some((_, lifetime)) = &mut self.slow {
+            *lifetime -= 1;
+            if *lifetime == 0 {
+                self.slow = None;
+            }
+        }
+
+        // A golden or poison feed which took the place of a feed leaves it empty when it is gone.
+        while self.n_placed_feeds() < self.n_feeds && self.n_free_cells() > 0 {
+            self.spawn_feed();
+        }
+    }
+
+    /// The result of a move, taking the end of the game into account.
+    fn finish(&mut self, result: MoveResult) -> MoveResult {
+        if !result.is_over() && self.n_placed_feeds() == 0 {
+            self.emit(GameEvent::Won);
+            return MoveResult::Won;
+        }
+        if result.is_over() {
+            self.emit(GameEvent::GameOver(result));
+        }
+        result
+    }
+
+    /// Send the event to the observers, forgetting the ones which are gone.
+    fn emit(&mut self, event: GameEvent) {
+        self.observers.retain(|tx| tx.send(event).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::mock::StepRng;
+
+    fn new_logic() -> SnakeGameLogic {
+        SnakeGameLogic::new(Size(20, 20), 1, 0)
+    }
+
+    fn set_body(logic: &mut SnakeGameLogic, body: &[Coord]) {
+        logic.snakes[0].body = body.iter().copied().collect();
+        logic.occupied = body.iter().copied().collect();
+    }
+
+    #[test]
+    fn new_feed_kind_follows_weights() {
+        let mut logic = new_logic();
+        logic.set_feed_weights(FeedWeights {
+            feed: 0,
+            golden: 0,
+            poison: 1,
+        });
+        logic.feeds = vec![Coord(5, 2)];
+        assert_eq!(logic.step(), MoveResult::Ate(Food::Feed));
+        assert!(logic.feeds().is_empty());
+        assert!(logic.poison().is_some());
+
+        // A normal feed takes the place of the poison feed after it is eaten.
+        logic.set_feed_weights(FeedWeights::default());
+        set_body(
+            &mut logic,
+            &[Coord(8, 8), Coord(7, 8), Coord(6, 8), Coord(5, 8)],
+        );
+        logic.poison = Some(Coord(9, 8));
+        assert_eq!(logic.step(), MoveResult::Ate(Food::Poison));
+        assert_eq!(logic.body().count(), 3);
+        assert_eq!(logic.poison(), None);
+        assert_eq!(logic.feeds().len(), 1);
+        assert_eq!(logic.score(), 0);
+    }
+
+    #[test]
+    fn feed_grows_snake_over_steps() {
+        let mut logic = new_logic();
+        logic.set_growth_per_food(3);
+        set_body(&mut logic, &[Coord(4, 2), Coord(3, 2), Coord(2, 2)]);
+        logic.feeds = vec![Coord(5, 2)];
+        logic.step();
+        assert_eq!(logic.body().count(), 4);
+        logic.step();
+        logic.step();
+        assert_eq!(logic.body().count(), 6);
+        logic.step();
+        assert_eq!(logic.body().count(), 6);
+
+        // The tail does not move away while growing.
+        let mut logic = new_logic();
+        logic.set_growth_per_food(3);
+        set_body(
+            &mut logic,
+            &[Coord(4, 3), Coord(4, 2), Coord(5, 2), Coord(5, 3)],
+        );
+        logic.snakes[0].dir = Direction::Down;
+        logic.feeds = vec![Coord(4, 4)];
+        logic.step();
+        logic.set_dir(Direction::Right);
+        logic.step();
+        logic.set_dir(Direction::Up);
+        assert_eq!(logic.step(), MoveResult::HitSelf);
+    }
+
+    #[test]
+    fn quick_feeds_multiply_points() {
+        let mut logic = new_logic();
+        logic.set_combo_window(Some(2));
+        logic.feeds = vec![Coord(5, 2)];
+        logic.step();
+        assert_eq!((logic.score(), logic.combo()), (1, 1));
+        logic.feeds = vec![Coord(7, 2)];
+        logic.step();
+        logic.step();
+        assert_eq!((logic.score(), logic.combo()), (3, 2));
+        logic.feeds = vec![Coord(9, 2)];
+        logic.step();
+        logic.step();
+        assert_eq!((logic.score(), logic.combo()), (6, 3));
+
+        // Too slow.
+        logic.feeds = vec![Coord(13, 2)];
+        logic.step();
+        logic.step();
+        assert_eq!(logic.combo(), 3);
+        logic.step();
+        assert_eq!(logic.combo(), 1);
+        logic.step();
+        assert_eq!((logic.score(), logic.combo()), (7, 1));
+    }
+
+    #[test]
+    fn feed_avoids_cell_ahead() {
+        let mut logic = new_logic();
+        logic.set_avoid_head(true);
+        logic.feeds.clear();
+        // Only the cell ahead of the head at (4, 2) and one more are free.
+        let free = [Coord(5, 2), Coord(10, 10)];
+        logic.obstacles = (1..19)
+            .flat_map(|y| (1..19).map(move |x| Coord(x, y)))
+            .filter(|c| !free.contains(c) && !logic.occupied.contains(c))
+            .collect();
+        for _ in 0..10 {
+            logic.spawn_feed();
+            assert_eq!(logic.feeds(), [Coord(10, 10)]);
+            logic.feeds.clear();
+        }
+
+        // It goes ahead if nowhere else is free.
+        logic.obstacles.insert(Coord(10, 10));
+        logic.spawn_feed();
+        assert_eq!(logic.feeds(), [Coord(5, 2)]);
+    }
+
+    #[test]
+    fn board_to_json() {
+        let mut logic = new_logic();
+        set_body(&mut logic, &[Coord(4, 2), Coord(3, 2)]);
+        logic.feeds = vec![Coord(9, 14)];
+        assert_eq!(
+            logic.to_json(),
+            r#"{"size":[20,20],"snake":[[4,2],[3,2]],"direction":"Right","feeds":[[9,14]],"score":0}"#
+        );
+    }
+
+    #[test]
+    fn random_coord_is_in_range() {
+        // Every draw of this RNG is one more than the last.
+        let mut rng = StepRng::new(0, 1);
+        assert_eq!(
+            Coord::rand(&mut rng, &Size(1, 1), &Size(18, 18)),
+            Coord(1, 2)
+        );
+        assert_eq!(Coord::rand(&mut rng, &Size(1, 1), &Size(2, 2)), Coord(1, 2));
+
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let c = Coord::rand(&mut rng, &Size(3, 5), &Size(4, 5));
+            assert!((3..=4).contains(&c.0) && c.1 == 5);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn random_coord_needs_valid_range() {
+        Coord::rand(&mut StepRng::new(0, 1), &Size(1, 3), &Size(2, 2));
+    }
+
+    #[test]
+    fn smallest_field_holds_snake_and_feed() {
+        let logic = SnakeGameLogic::new(Size(MIN_FIELD_LEN, MIN_FIELD_LEN), 1, 0);
+        for p in logic.body().chain(logic.feeds()) {
+            assert!(logic.is_inner_field(p));
+        }
+        assert!(!logic.snakes[0].body.contains(&logic.feeds()[0]));
+    }
+
+    #[test]
+    #[should_panic(expected = "the field must be at least")]
+    fn too_small_field_is_rejected() {
+        SnakeGameLogic::new(Size(20, MIN_FIELD_LEN - 1), 1, 0);
+    }
+
+    #[test]
+    fn initial_len_makes_straight_body() {
+        let logic = SnakeGameLogic::with_initial_len(Size(10, 10), 1, 0, 7);
+        let body: Vec<Coord> = logic.body().copied().collect();
+        assert_eq!(body, (2..=8).rev().map(|x| Coord(x, 2)).collect::<Vec<_>>());
+        assert_eq!(logic.score(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not fit")]
+    fn too_long_snake_is_rejected() {
+        SnakeGameLogic::with_initial_len(Size(10, 10), 1, 0, 8);
+    }
+
+    #[test]
+    fn game_from_start() {
+        let start = Start {
+            head: Coord(10, 10),
+            dir: Direction::Up,
+            feed: Coord(3, 3),
+        };
+        let logic = SnakeGameLogic::with_start(Size(20, 20), 1, 0, 3, start);
+        assert!(logic
+            .body()
+            .eq(&[Coord(10, 10), Coord(10, 11), Coord(10, 12)]));
+        assert_eq!(logic.direction(), Direction::Up);
+        assert_eq!(logic.feeds(), [Coord(3, 3)]);
+
+        // The body goes into the wall, the head is on the wall, or the feed is on the snake.
+        let size = Size(20, 20);
+        let fits = |start: Start| start.check(size, 3).is_ok();
+        let right = Start {
+            dir: Direction::Right,
+            ..start
+        };
+        assert!(!fits(Start {
+            head: Coord(2, 5),
+            ..right
+        }));
+        assert!(!fits(Start {
+            head: Coord(10, 0),
+            ..start
+        }));
+        assert!(!fits(Start {
+            feed: Coord(10, 11),
+            ..start
+        }));
+
+        let random = Start::random(size, 3, 7);
+        assert!(fits(random));
+        assert_eq!(Start::random(size, 3, 7), random);
+    }
+
+    #[test]
+    fn adjascent_does_not_underflow() {
+        assert_eq!(Coord(0, 5).adjascent(&Direction::Left), None);
+        assert_eq!(Coord(5, 0).adjascent(&Direction::Up), None);
+        assert_eq!(Coord(u16::MAX, 5).adjascent(&Direction::Right), None);
+        assert_eq!(Coord(5, 5).adjascent(&Direction::Up), Some(Coord(5, 4)));
+    }
+
+    #[test]
+    fn moving_into_wall_is_game_over() {
+        let mut logic = new_logic();
+        logic.set_dir(Direction::Up);
+        assert_eq!(logic.step(), MoveResult::Alive);
+        assert_eq!(logic.step(), MoveResult::HitWall);
+    }
+
+    #[test]
+    fn wrap_goes_through_wall() {
+        let mut logic = new_logic();
+        logic.set_wrap(true);
+        set_body(&mut logic, &[Coord(18, 2), Coord(17, 2), Coord(16, 2)]);
+        assert_eq!(logic.step(), MoveResult::Alive);
+        assert_eq!(logic.body().next(), Some(&Coord(1, 2)));
+    }
+
+    #[test]
+    fn zen_ignores_crash() {
+        let mut logic = new_logic();
+        logic.set_zen(true);
+        // The head is at (4, 3) and the body goes around over it.
+        let body = [
+            Coord(4, 3),
+            Coord(5, 3),
+            Coord(5, 2),
+            Coord(4, 2),
+            Coord(3, 2),
+        ];
+        set_body(&mut logic, &body);
+        logic.feeds = vec![Coord(3, 3)];
+        // Moves into the body are ignored, and the snake stays.
+        assert_eq!(logic.step(), MoveResult::Alive);
+        logic.set_dir(Direction::Up);
+        assert_eq!(logic.step(), MoveResult::Alive);
+        assert!(logic.body().eq(&body));
+
+        logic.set_dir(Direction::Left);
+        assert_eq!(logic.step(), MoveResult::Ate(Food::Feed));
+        assert_eq!(logic.score(), 1);
+        // It goes through the wall instead of hitting it.
+        for _ in 0..3 {
+            assert_eq!(logic.step(), MoveResult::Alive);
+        }
+        assert_eq!(logic.body().next(), Some(&Coord(18, 3)));
+    }
+
+    #[test]
+    fn read_state() {
+        let mut logic = new_logic();
+        assert_eq!(logic.field_size(), Size(20, 20));
+        assert_eq!(logic.direction(), Direction::Right);
+        assert_eq!(logic.feed(), Some(Coord(10, 10)));
+        assert!(logic.body().eq(&[Coord(4, 2), Coord(3, 2), Coord(2, 2)]));
+
+        logic.set_dir(Direction::Down);
+        logic.step();
+        assert_eq!(logic.direction(), Direction::Down);
+        logic.feeds.clear();
+        assert_eq!(logic.feed(), None);
+    }
+
+    #[test]
+    fn wrap_only_sideways() {
+        let mut logic = new_logic();
+        logic.set_wrap_x(true);
+        set_body(&mut logic, &[Coord(18, 2), Coord(17, 2), Coord(16, 2)]);
+        assert_eq!(logic.step(), MoveResult::Alive);
+        assert_eq!(logic.body().next(), Some(&Coord(1, 2)));
+        logic.set_dir(Direction::Up);
+        assert_eq!(logic.step(), MoveResult::Alive);
+        assert_eq!(logic.step(), MoveResult::HitWall);
+    }
+
+    #[test]
+    fn normal_move_keeps_length() {
+        let mut logic = new_logic();
+        assert_eq!(logic.step(), MoveResult::Alive);
+        assert_eq!(logic.body().count(), 3);
+        assert_eq!(logic.body().next(), Some(&Coord(5, 2)));
+        assert_eq!(logic.score(), 0);
+    }
+
+    #[test]
+    fn eating_feed_grows_body() {
+        let mut logic = new_logic();
+        logic.feeds = vec![Coord(5, 2)];
+        assert_eq!(logic.step(), MoveResult::Ate(Food::Feed));
+        assert_eq!(logic.body().count(), 4);
+        assert_eq!(logic.score(), 1);
+
+        // A new feed is put on a free cell.
+        assert_eq!(logic.feeds().len(), 1);
+        assert!(!logic.snakes[0].body.contains(&logic.feeds()[0]));
+
+        // The same seed puts the feed at the same place.
+        let mut other = new_logic();
+        other.feeds = vec![Coord(5, 2)];
+        assert_eq!(other.step(), MoveResult::Ate(Food::Feed));
+        assert_eq!(logic.feeds(), other.feeds());
+    }
+
+    #[test]
+    fn quick_turns_are_applied_one_by_one() {
+        // Moving right. Turning down and then left must not fold the snake back on itself.
+        let mut logic = new_logic();
+        logic.set_dir(Direction::Down);
+        logic.set_dir(Direction::Left);
+        assert_eq!(logic.step(), MoveResult::Alive);
+        assert_eq!(logic.get_dir(), &Direction::Down);
+        assert_eq!(logic.step(), MoveResult::Alive);
+        assert_eq!(logic.get_dir(), &Direction::Left);
+        assert_eq!(logic.body().next(), Some(&Coord(3, 3)));
+    }
+
+    #[test]
+    fn reversing_is_ignored() {
+        let mut logic = new_logic();
+        logic.set_dir(Direction::Left);
+        assert_eq!(logic.step(), MoveResult::Alive);
+        assert_eq!(logic.get_dir(), &Direction::Right);
+    }
+
+    #[test]
+    fn running_into_body_is_game_over() {
+        let mut logic = new_logic();
+        set_body(
+            &mut logic,
+            &[
+                Coord(3, 3),
+                Coord(4, 3),
+                Coord(4, 4),
+                Coord(3, 4),
+                Coord(2, 4),
+            ],
+        );
+        logic.snakes[0].dir = Direction::Left;
+        logic.set_dir(Direction::Down);
+        assert_eq!(logic.step(), MoveResult::HitSelf);
+    }
+
+    #[test]
+    fn head_can_follow_tail() {
+        // The tail moves away at the same step, so it is not a collision.
+        let mut logic = new_logic();
+        set_body(
+            &mut logic,
+            &[Coord(3, 3), Coord(4, 3), Coord(4, 4), Coord(3, 4)],
+        );
+        logic.snakes[0].dir = Direction::Left;
+        logic.set_dir(Direction::Down);
+        assert_eq!(logic.step(), MoveResult::Alive);
+        assert_eq!(logic.body().next(), Some(&Coord(3, 4)));
+    }
+
+    #[test]
+    fn occupied_cells_follow_body() {
+        let mut logic = SnakeGameLogic::new(Size(12, 12), 5, 42);
+        logic.set_wrap(true);
+        let dirs = [
+            Direction::Down,
+            Direction::Left,
+            Direction::Up,
+            Direction::Right,
+        ];
+        for i in 0..1000 {
+            if i % 7 == 0 {
+                logic.set_dir(dirs[i / 7 % dirs.len()]);
+            }
+            if logic.step().is_over() {
+                break;
+            }
+            let body: HashSet<Coord> = logic.body().copied().collect();
+            assert_eq!(body.len(), logic.body().count());
+            assert_eq!(body, logic.occupied);
+        }
+    }
+
+    #[test]
+    fn filling_field_is_victory() {
+        // Only one free cell is left, and the feed is on it.
+        // The field is shrunk after `new` to fill it with a short body.
+        let mut logic = SnakeGameLogic::new(Size(6, 6), 1, 0);
+        logic.field_size = Size(6, 4);
+        set_body(
+            &mut logic,
+            &[
+                Coord(3, 2),
+                Coord(2, 2),
+                Coord(1, 2),
+                Coord(1, 1),
+                Coord(2, 1),
+                Coord(3, 1),
+                Coord(4, 1),
+            ],
+        );
+        logic.feeds = vec![Coord(4, 2)];
+        assert_eq!(logic.step(), MoveResult::Won);
+        assert!(logic.feeds().is_empty());
+    }
+
+    #[test]
+    fn second_snake_does_not_cover_feed() {
+        // The feed starts at (5, 3), where the second snake is put.
+        let mut logic = SnakeGameLogic::new(Size(10, 6), 1, 0);
+        logic.add_snake();
+        assert_eq!(logic.n_snakes(), 2);
+        assert_eq!(logic.body_of(1).next(), Some(&Coord(5, 3)));
+        assert_eq!(*logic.get_dir_of(1), Direction::Left);
+        assert_eq!(logic.feeds().len(), 1);
+        assert!(logic.body_of(1).all(|c| *c != logic.feeds()[0]));
+    }
+
+    #[test]
+    fn snake_hits_other_snake() {
+        let mut logic = SnakeGameLogic::new(Size(10, 6), 1, 0);
+        logic.add_snake();
+        logic.feeds = vec![Coord(1, 1)];
+        // The first snake moves into the cell the second one is heading to.
+        logic.set_dir_of(0, Direction::Down);
+        assert_eq!(
+            logic.step_each(),
+            vec![MoveResult::Alive, MoveResult::HitSnake]
+        );
+    }
+
+    #[test]
+    fn feed_moves_when_not_eaten() {
+        let mut logic = new_logic();
+        logic.set_feed_ttl(Some(2));
+        assert_eq!(logic.feed_lifetime(), Some((2, 2)));
+
+        logic.step();
+        assert_eq!(logic.feeds(), [Coord(10, 10)]);
+        assert_eq!(logic.feed_lifetime(), Some((1, 2)));
+        logic.step();
+        assert_ne!(logic.feeds(), [Coord(10, 10)]);
+        assert!(!logic.snakes[0].body.contains(&logic.feeds()[0]));
+        assert_eq!(logic.feed_lifetime(), Some((2, 2)));
+    }
+
+    #[test]
+    fn feed_drifts_to_next_cell() {
+        let mut logic = new_logic();
+        logic.set_feed_drift(Some(2));
+        logic.step();
+        assert_eq!(logic.feeds(), [Coord(10, 10)]);
+        logic.step();
+        let Coord(x, y) = logic.feeds()[0];
+        assert_eq!(x.abs_diff(10) + y.abs_diff(10), 1);
+
+        // A feed in a corner closed in by obstacles stays.
+        logic.feeds = vec![Coord(1, 1)];
+        logic.obstacles.extend([Coord(2, 1), Coord(1, 2)]);
+        logic.step();
+        logic.step();
+        assert_eq!(logic.feeds(), [Coord(1, 1)]);
+    }
+
+    #[test]
+    fn reverse_swaps_controls_for_a_while() {
+        let mut logic = new_logic();
+        logic.set_reverse(true);
+        let head = logic.snakes[0].body[0];
+        logic.reverse = Some(Coord(head.0 + 1, head.1));
+        assert_eq!(logic.step(), MoveResult::Ate(Food::Reverse));
+        assert_eq!(logic.score(), 0);
+        assert!(logic
+            .reverse()
+            .is_some_and(|p| p != Coord(head.0 + 1, head.1)));
+        assert_eq!(logic.reversed_of(0), 30);
+
+        // Up turns the snake down.
+        logic.set_dir(Direction::Up);
+        logic.step();
+        assert_eq!(logic.direction(), Direction::Down);
+        assert_eq!(logic.reversed_of(0), 29);
+
+        // The last reversed step.
+        logic.snakes[0].reversed = 1;
+        logic.set_dir(Direction::Left);
+        assert_eq!(logic.step(), MoveResult::Alive);
+        assert_eq!(logic.direction(), Direction::Right);
+        assert_eq!(logic.reversed_of(0), 0);
+        logic.set_dir(Direction::Down);
+        logic.step();
+        assert_eq!(logic.direction(), Direction::Down);
+    }
+
+    #[test]
+    fn invincible_snake_crosses_body() {
+        let mut logic = new_logic();
+        let body = [5, 4, 3, 2, 1].map(|x| Coord(x, 5));
+        set_body(&mut logic, &body);
+        logic.snakes[0].invincible = 10;
+
+        // Loop back over the body, then go away from it.
+        for dir in [Direction::Down, Direction::Left, Direction::Up] {
+            logic.set_dir(dir);
+            assert_eq!(logic.step(), MoveResult::Alive);
+        }
+        assert_eq!(logic.snakes[0].body[0], Coord(4, 5));
+        for _ in 0..4 {
+            assert_eq!(logic.step(), MoveResult::Alive);
+        }
+        let cells: HashSet<Coord> = logic.snakes[0].body.iter().copied().collect();
+        assert_eq!(logic.occupied, cells);
+        assert_eq!(logic.snakes[0].crossed, 0);
+
+        // It does not go through the wall.
+        assert_eq!(logic.step(), MoveResult::HitWall);
+
+        // Nor the body when it has worn off.
+        let mut logic = new_logic();
+        set_body(&mut logic, &body);
+        for dir in [Direction::Down, Direction::Left] {
+            logic.set_dir(dir);
+            logic.step();
+        }
+        logic.set_dir(Direction::Up);
+        assert_eq!(logic.step(), MoveResult::HitSelf);
+    }
+
+    #[test]
+    fn slow_motion_wears_off() {
+        let mut logic = new_logic();
+        logic.set_slow(true);
+        let head = logic.snakes[0].body[0];
+        logic.slow = Some((Coord(head.0 + 1, head.1), 10));
+        assert_eq!(logic.step(), MoveResult::Ate(Food::Slow));
+        assert_eq!(logic.slow(), None);
+        assert_eq!(logic.slow_left(), 40);
+
+        logic.set_wrap(true);
+        for _ in 0..39 {
+            assert_eq!(logic.step(), MoveResult::Alive);
+        }
+        assert_eq!(logic.slow_left(), 1);
+        logic.step();
+        assert_eq!(logic.slow_left(), 0);
+    }
+
+    #[test]
+    fn observer_receives_events() {
+        let mut logic = new_logic();
+        let events = logic.subscribe();
+        let Coord(x, y) = logic.snakes[0].body[0];
+        logic.feeds = vec![Coord(x + 1, y)];
+        logic.step();
+        logic.set_dir(Direction::Up);
+        logic.step();
+        logic.step();
+
+        let player = 0;
+        let head = Coord(x + 1, y);
+        let food = Food::Feed;
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            [
+                GameEvent::Moved { player, head },
+                GameEvent::Grew { player, len: 4 },
+                GameEvent::Ate { player, food },
+                GameEvent::Moved {
+                    player,
+                    head: Coord(x + 1, y - 1)
+                },
+                GameEvent::GameOver(MoveResult::HitWall),
+            ]
+        );
+    }
+
+    #[test]
+    fn field_without_walls() {
+        let mut logic = SnakeGameLogic::without_walls(Size(20, 10), 3, 0, 3);
+        let in_view = |logic: &SnakeGameLogic, c: &Coord| {
+            let (Coord(x, y), Size(w, h)) = logic.view().unwrap();
+            (x..x + w).contains(&c.0) && (y..y + h).contains(&c.1)
+        };
+        assert_eq!(logic.feeds().len(), 3);
+        assert!(logic.feeds().iter().all(|f| in_view(&logic, f)));
+
+        // The snake goes anywhere, and the view follows the head.
+        logic.feeds = vec![Coord(1, 1)];
+        logic.set_dir(Direction::Up);
+        for _ in 0..100 {
+            assert_eq!(logic.step(), MoveResult::Alive);
+        }
+        let head = logic.snakes[0].body[0];
+        let (Coord(x, y), _) = logic.view().unwrap();
+        assert_eq!((head.0 - x, head.1 - y), (10, 5));
+
+        // A new feed appears in the view.
+        logic.feeds = vec![Coord(head.0, head.1 - 1)];
+        assert_eq!(logic.step(), MoveResult::Ate(Food::Feed));
+        assert!(in_view(&logic, &logic.feeds()[0]));
+    }
+
+    #[test]
+    fn feeds_are_kept_without_walls() {
+        let mut logic = SnakeGameLogic::without_walls(Size(20, 10), 3, 0, 3);
+        logic.set_feed_weights(FeedWeights {
+            feed: 0,
+            golden: 1,
+            poison: 0,
+        });
+        let Coord(x, y) = logic.snakes[0].body[0];
+        logic.feeds[0] = Coord(x + 1, y);
+        assert_eq!(logic.step(), MoveResult::Ate(Food::Feed));
+        assert_eq!(logic.feeds().len(), 2);
+
+        // A normal feed takes the place of the golden one after it is eaten.
+        logic.set_feed_weights(FeedWeights::default());
+        logic.golden = Some((Coord(x + 2, y), 10));
+        assert_eq!(logic.step(), MoveResult::Ate(Food::Golden));
+        assert_eq!(logic.feeds().len(), 3);
+    }
+
+    #[test]
+    fn bombs_appear_and_explode() {
+        let mut logic = new_logic();
+        logic.set_bomb_interval(Some(1));
+        for _ in 0..MAX_BOMBS + 2 {
+            logic.step();
+        }
+        assert_eq!(logic.bombs().count(), MAX_BOMBS);
+        for bomb in logic.bombs() {
+            assert!(!logic.snakes[0].body.contains(bomb));
+            assert!(!logic.feeds().contains(bomb));
+        }
+
+        let ahead = logic.next_cell(&logic.snakes[0].body[0], &logic.direction());
+        logic.bombs.insert(ahead.unwrap());
+        assert_eq!(logic.step(), MoveResult::HitBomb);
+    }
+
+    #[test]
+    fn crash_takes_life() {
+        let mut logic = new_logic();
+        logic.set_lives(2);
+        logic.set_dir(Direction::Up);
+        logic.step();
+        assert_eq!(logic.step(), MoveResult::LostLife);
+        assert_eq!(logic.lives_left(), 1);
+        assert_eq!(logic.body().next(), Some(&Coord(4, 2)));
+        assert_eq!(*logic.get_dir(), Direction::Right);
+
+        // Crashing right after the respawn only stops the snake.
+        logic.set_dir(Direction::Up);
+        logic.step();
+        assert_eq!(logic.step(), MoveResult::Alive);
+        assert_eq!(logic.body().next(), Some(&Coord(4, 1)));
+        while logic.is_invulnerable_of(0) {
+            logic.step();
+        }
+        assert_eq!(logic.step(), MoveResult::HitWall);
+    }
+
+    #[test]
+    fn grace_period_forgives_crash() {
+        let mut logic = new_logic();
+        logic.set_grace(3);
+        logic.set_dir(Direction::Up);
+        logic.step();
+        assert_eq!(logic.grace_left(), 2);
+        assert_eq!(logic.step(), MoveResult::Alive);
+        assert_eq!(logic.step(), MoveResult::Alive);
+        assert_eq!(logic.ticks(), 3);
+        assert_eq!(logic.grace_left(), 0);
+        assert_eq!(logic.step(), MoveResult::HitWall);
+
+        // The snake still dies by hitting itself after the grace period.
+        let mut logic = new_logic();
+        logic.set_grace(1);
+        logic.step();
+        set_body(
+            &mut logic,
+            &[
+                Coord(5, 5),
+                Coord(5, 6),
+                Coord(6, 6),
+                Coord(6, 5),
+                Coord(6, 4),
+            ],
+        );
+        logic.snakes[0].dir = Direction::Up;
+        logic.set_dir(Direction::Right);
+        assert_eq!(logic.step(), MoveResult::HitSelf);
+    }
+
+    #[test]
+    fn portal_takes_head_to_other() {
+        let mut logic = new_logic();
+        let head = *logic.body().next().unwrap();
+        let ahead = Coord(head.0 + 1, head.1);
+        logic.portals = vec![(ahead, Coord(10, 15))];
+        logic.feeds = vec![Coord(1, 1)];
+        assert_eq!(logic.step(), MoveResult::Alive);
+        assert_eq!(logic.body().next(), Some(&Coord(11, 15)));
+        assert_eq!(logic.body().nth(1), Some(&head));
+
+        // A chain of portals is followed to the end, and the body at the exit is a crash.
+        let mut logic = new_logic();
+        let head = *logic.body().next().unwrap();
+        let tail = *logic.body().last().unwrap();
+        let ahead = Coord(head.0 + 1, head.1);
+        logic.portals = vec![
+            (ahead, Coord(10, 15)),
+            (Coord(11, 15), Coord(tail.0 - 1, tail.1)),
+        ];
+        // The tail stays to grow.
+        logic.snakes[0].growing = 1;
+        assert_eq!(logic.step(), MoveResult::HitSelf);
+    }
+
+    #[test]
+    #[should_panic]
+    fn feed_ttl_is_positive() {
+        new_logic().set_feed_ttl(Some(0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn feed_drift_is_positive() {
+        new_logic().set_feed_drift(Some(0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn bomb_interval_is_positive() {
+        new_logic().set_bomb_interval(Some(0));
+    }
+
+    #[test]
+    fn game_on_level() {
+        let level = Level::parse(
+            "\
+######
+#  @ #
+# #  #
+# ^  #
+#    #
+######",
+        )
+        .unwrap();
+        let mut logic = SnakeGameLogic::from_level(&level, 1, 0, 2);
+        let body: Vec<Coord> = logic.body().copied().collect();
+        assert_eq!(body, [Coord(2, 3), Coord(2, 4)]);
+        assert_eq!(logic.feeds(), [Coord(3, 1)]);
+        assert_eq!(logic.step(), MoveResult::HitObstacle);
+    }
+
+    #[test]
+    fn step_back_undoes_step() {
+        let mut logic = new_logic();
+        logic.set_history_len(2);
+        logic.set_dir(Direction::Up);
+        logic.step();
+        assert_eq!(logic.step(), MoveResult::HitWall);
+
+        assert!(logic.step_back());
+        assert_eq!(logic.body().next(), Some(&Coord(4, 1)));
+        assert_eq!(*logic.get_dir(), Direction::Up);
+        logic.set_dir(Direction::Right);
+        assert_eq!(logic.step(), MoveResult::Alive);
+
+        // Only the last two steps are kept.
+        assert!(logic.step_back());
+        assert!(logic.step_back());
+        assert!(!logic.step_back());
+        assert_eq!(logic.body().next(), Some(&Coord(4, 2)));
+    }
+}
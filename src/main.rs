@@ -1,280 +1,2198 @@
-use rand::prelude::*;
-use std::io::{stdout, Stdout, Write};
-use std::sync::mpsc::{self, Receiver, Sender};
-use std::thread;
-use std::time::Duration;
-use std::{collections::LinkedList, io::stdin};
-use termion::event::Key;
-use termion::input::TermRead;
-use termion::raw::IntoRawMode;
-
-#[derive(Copy, Clone, Hash, Eq, PartialEq)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
+mod cli;
+mod config;
+mod daily;
+// The bench draws frames with these.
+pub(crate) mod input;
+mod leaderboard;
+mod menu;
+pub(crate) mod render;
+mod replay;
+mod save;
+mod stats;
+
+use clap::{CommandFactory, Parser, ValueEnum};
+use cli::Cli;
+use config::Config;
+use crossterm::terminal;
+use input::{Input, InputSource, Keymap, TermInput};
+use leaderboard::{Leaderboard, MAX_NAME_LEN};
+use menu::Menu;
+use render::{CastRenderer, FileRenderer, Prompts, Renderer, Scene, TermRenderer, Theme};
+use replay::{Ghost, Replay, Turn};
+use save::{SaveFile, SavedGame};
+use snake_game::{
+    autopilot, max_initial_len, Coord, Direction, FeedWeights, Food, Level, MoveResult, Size,
+    SnakeGameLogic, Start, MIN_FIELD_LEN,
+};
+use stats::Stats;
+use std::cmp::Ordering;
+use std::collections::{HashSet, VecDeque};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant};
+
+/// The best score ever made, stored in `~/.snake_highscore`.
+struct HighScore {
+    path: Option<PathBuf>,
+    value: usize,
 }
 
-impl Direction {
-    fn opposite(&self) -> Self {
-        match self {
-            Direction::Up => Direction::Down,
-            Direction::Down => Direction::Up,
-            Direction::Left => Direction::Right,
-            Direction::Right => Direction::Left,
+impl HighScore {
+    /// Load the high score.
+    /// A missing or broken file is treated as 0.
+    fn load() -> Self {
+        let path = env::var_os("HOME").map(|home| PathBuf::from(home).join(".snake_highscore"));
+        let value = path
+            .as_ref()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .and_then(|s| s.trim().parse().ok())
+            .unwrap_or(0);
+        Self { path, value }
+    }
+
+    fn get(&self) -> usize {
+        self.value
+    }
+
+    /// Record the score and save it if it beats the high score.
+    fn update(&mut self, score: usize) {
+        if score <= self.value {
+            return;
+        }
+        self.value = score;
+        if let Some(path) = &self.path {
+            // Failing to save must not end the game.
+            let _ = fs::write(path, format!("{}\n", score));
         }
     }
 }
 
-#[derive(Copy, Clone, Hash, Eq, PartialEq, PartialOrd)]
-struct Size(u16, u16);
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum SnakeGameEvent {
+    ChangeDir(Direction),
+    /// Steer the snake of the second player.
+    ChangeDir2(Direction),
+    /// Time for the next move, or the next number of the countdown.
+    Tick,
+    /// Draw the frame again without moving, to keep the time shown up to date.
+    Redraw,
+    TogglePause,
+    Restart,
+    /// Make the game slower by a step.
+    Slower,
+    /// Make the game faster by a step.
+    Faster,
+    /// Move one step while paused.
+    StepOnce,
+    /// Undo the last step in the practice mode.
+    Undo,
+    /// Show or hide the help bar.
+    ToggleHelp,
+    /// Show the panel of the controls and the settings.
+    ShowControls,
+    /// Choose the item of the menu, or finish typing.
+    Select,
+    /// A character typed, such as of a name.
+    Typed(char),
+    /// Erase the character typed last.
+    Erase,
+    /// Answer to the question to quit.
+    Yes,
+    No,
+    /// The terminal is resized to the columns and rows.
+    Resize(u16, u16),
+    Quit,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum GameState {
+    /// Counting down the seconds before the game starts, or showing "Go!" at 0.
+    Countdown(u32),
+    Playing,
+    Paused,
+    /// The game ended for the reason.
+    Over(MoveResult),
+    /// The time limit of the time attack ran out.
+    TimeUp,
+    /// The replay reached the end of the recording, which scored the number.
+    ReplayEnd(usize),
+    /// The two-player game was won by the player, or is a draw if `None`.
+    Winner(Option<usize>),
+}
+
+impl GameState {
+    fn is_over(&self) -> bool {
+        matches!(
+            self,
+            GameState::Over(_) | GameState::TimeUp | GameState::ReplayEnd(_) | GameState::Winner(_)
+        )
+    }
+}
 
-#[derive(Copy, Clone, Hash, Eq, PartialEq, PartialOrd)]
-struct Coord(u16, u16);
+/// How the last game ended, which the exit code tells.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Outcome {
+    /// The player quit before the game was over, or the recording played ended.
+    Quit,
+    /// The snake crashed, the time ran out, or the two-player game was a draw.
+    GameOver,
+    /// The snake filled the field, or a player won the two-player game.
+    Won,
+}
 
-impl Coord {
-    fn adjascent(&self, dir: &Direction) -> Self {
-        match dir {
-            Direction::Up => Self(self.0, self.1 - 1),
-            Direction::Down => Self(self.0, self.1 + 1),
-            Direction::Left => Self(self.0 - 1, self.1),
-            Direction::Right => Self(self.0 + 1, self.1),
+impl Outcome {
+    fn of(state: GameState) -> Self {
+        match state {
+            GameState::Over(MoveResult::Won) | GameState::Winner(Some(_)) => Outcome::Won,
+            GameState::Over(_) | GameState::TimeUp | GameState::Winner(None) => Outcome::GameOver,
+            GameState::Countdown(_)
+            | GameState::Playing
+            | GameState::Paused
+            | GameState::ReplayEnd(_) => Outcome::Quit,
         }
     }
 
-    fn rand(min: &Size, max: &Size) -> Self {
-        assert!(min <= max);
-        let mut rng = rand::thread_rng();
-        Self(
-            min.0 + (rng.gen::<u16>() % (1 + max.0 - min.0)),
-            min.1 + (rng.gen::<u16>() % (1 + max.1 - min.1)),
-        )
+    /// The exit code, after 1 for errors and 2 for invalid options.
+    fn code(self) -> i32 {
+        match self {
+            Outcome::Quit => 0,
+            Outcome::GameOver => 3,
+            Outcome::Won => 4,
+        }
     }
 }
 
-struct SnakeGameLogic {
-    field_size: Size,
-    /// Body of snake.
-    ///  body[0] is the head of the snake.
-    ///  body[body.len() - 1] is the tail of the snake.
-    body: LinkedList<Coord>,
-    pos_feed: Coord,
-    dir: Direction,
+/// A bundle of settings, which the config file and the options can still change one by one.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub(crate) enum Difficulty {
+    /// Slow, and the walls let the snake through.
+    Easy,
+    /// The default settings.
+    Normal,
+    /// Fast, with obstacles.
+    Hard,
 }
 
-impl SnakeGameLogic {
-    fn new(field_size: Size) -> Self {
-        Self {
-            field_size,
-            body: [Coord(4, 2), Coord(3, 2), Coord(2, 2)].into(),
-            pos_feed: Coord(10, 10),
-            dir: Direction::Right,
+impl Difficulty {
+    fn tick(&self) -> Duration {
+        Duration::from_millis(match self {
+            Difficulty::Easy => 200,
+            Difficulty::Normal => 150,
+            Difficulty::Hard => 80,
+        })
+    }
+
+    fn obstacles(&self) -> usize {
+        match self {
+            Difficulty::Easy | Difficulty::Normal => 0,
+            Difficulty::Hard => 15,
         }
     }
 
-    fn get_dir(&self) -> &Direction {
-        &self.dir
+    fn wrap(&self) -> bool {
+        *self == Difficulty::Easy
     }
 
-    fn is_inner_field(&self, c: &Coord) -> bool {
-        let Size(w, h) = self.field_size;
-        (1..w - 1).contains(&c.0) && (1..h - 1).contains(&c.1)
+    /// The one after this, going round.
+    fn next(&self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
     }
+}
 
-    fn score(&self) -> usize {
-        self.body.len() - 3
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        })
     }
+}
 
-    /// Move head toward the direction.
-    /// Return false if game is over.
-    fn r#move(&mut self, dir: Direction) -> bool {
-        let head = self.body.front().unwrap();
+/// Bounds and step of the speed changed while playing, in percent.
+const MIN_SPEED: u32 = 25;
+const MAX_SPEED: u32 = 400;
+const SPEED_STEP: u32 = 25;
 
-        // Update direction
-        if self.dir.opposite() != dir {
-            self.dir = dir;
-        }
+/// Interval between the frames drawn while playing, which is shorter than the moves.
+const FRAME_INTERVAL: Duration = Duration::from_millis(50);
 
-        let adj = head.adjascent(&self.dir);
+/// Time each number of the countdown is shown for. "Go!" is shown for half of it.
+const COUNTDOWN_STEP: Duration = Duration::from_secs(1);
 
-        // Update the direction except when the direction is not opposite from the current direction.
-        if !self.is_inner_field(&adj) {
-            return false;
-        }
+/// Number of steps which can be undone in the practice mode.
+const PRACTICE_HISTORY_LEN: usize = 100;
 
-        // Move or Grow
-        self.body.push_front(adj);
-        if adj == self.pos_feed {
-            let max = Size(self.field_size.0 - 2, self.field_size.1 - 2);
-            'outer: loop {
-                let next_feed_candidate = Coord::rand(&Size(1, 1), &max);
-                for p in &self.body {
-                    if p == &next_feed_candidate {
-                        continue 'outer;
-                    }
-                }
-                self.pos_feed = next_feed_candidate;
-                break;
-            }
-        } else {
-            self.body.pop_back();
+/// Time spent playing, which does not advance while the game is paused or over.
+struct Stopwatch {
+    total: Duration,
+    /// When it was started last, if it is running.
+    since: Option<Instant>,
+}
+
+impl Stopwatch {
+    fn start() -> Self {
+        Self {
+            total: Duration::ZERO,
+            since: Some(Instant::now()),
         }
+    }
 
-        // Collidge with body.
-        for p in self.body.iter().skip(1) {
-            // adj is the head.
-            if p == &adj {
-                return false;
-            }
+    /// A stopped stopwatch which has counted the time.
+    fn stopped_at(total: Duration) -> Self {
+        Self { total, since: None }
+    }
+
+    fn stop(&mut self) {
+        if let Some(since) = self.since.take() {
+            self.total += since.elapsed();
         }
+    }
 
-        true
+    fn resume(&mut self) {
+        self.since.get_or_insert_with(Instant::now);
     }
-}
 
-enum SnakeGameEvent {
-    ChangeDir(Direction),
-    Render,
-    Quit,
+    fn elapsed(&self) -> Duration {
+        self.total + self.since.map_or(Duration::ZERO, |since| since.elapsed())
+    }
 }
 
 struct SnakeGameControler {
     logic: SnakeGameLogic,
-    event_tx: Sender<SnakeGameEvent>,
-    event_rx: Receiver<SnakeGameEvent>,
+    state: GameState,
+    time: Stopwatch,
+    high_score: HighScore,
+    leaderboard: Leaderboard,
+    /// The name being typed for the leaderboard, after a game which got on it.
+    name: Option<String>,
+    stats: Stats,
+    /// Where to save the game if the player quits in the middle.
+    save: SaveFile,
+    /// Feeds eaten in the current game.
+    eaten: u64,
+    /// Feeds eaten before each of the steps which can be undone, the last step at the back.
+    eaten_before: VecDeque<u64>,
+    args: Args,
+    /// Recording of the current game.
+    record: Replay,
+    /// The recorded game played along with the current one.
+    ghost: Option<Ghost>,
+    /// Cells the tails left in the last few moves, with the number of moves since.
+    trail: Vec<(Coord, usize)>,
+    /// Index of the level being played in `args.levels`.
+    level: usize,
+    /// Speed of the game in percent of `args.tick`, changed while playing.
+    speed: u32,
+    /// Show the help bar.
+    help: bool,
+    /// The controls shown in the help bar.
+    help_line: String,
+    /// The keys to answer the messages over the field.
+    prompts: Prompts,
+    /// The state to go back to if the player does not quit, while asking whether to quit.
+    quitting: Option<GameState>,
+    /// Lines telling the keys, for the panel of the controls.
+    controls: Vec<String>,
+    /// The state to go back to when the panel of the controls is closed, while it is shown.
+    panel: Option<GameState>,
 }
 
 impl SnakeGameControler {
-    fn new() -> Self {
-        let (tx, rx) = mpsc::channel();
-        Self {
-            logic: SnakeGameLogic::new(Size(20, 20)),
-            event_tx: tx,
-            event_rx: rx,
+    fn new(args: Args) -> Self {
+        let (logic, record) = Self::new_game(&args);
+        let mut ctrl = Self {
+            logic,
+            ghost: Self::new_ghost(&args),
+            trail: Vec::new(),
+            state: GameState::Playing,
+            time: Stopwatch::start(),
+            high_score: HighScore::load(),
+            leaderboard: Leaderboard::load(),
+            name: None,
+            stats: Stats::load(),
+            save: SaveFile::new(),
+            eaten: 0,
+            eaten_before: VecDeque::new(),
+            help: args.help,
+            help_line: String::new(),
+            prompts: Keymap::default().prompts(),
+            args,
+            record,
+            level: 0,
+            speed: 100,
+            quitting: None,
+            controls: Vec::new(),
+            panel: None,
+        };
+        ctrl.set_state(Self::start_state(&ctrl.args));
+        ctrl
+    }
+
+    /// The state a new game starts in, which is the countdown unless it is turned off.
+    fn start_state(args: &Args) -> GameState {
+        match args.countdown {
+            0 => GameState::Playing,
+            secs => GameState::Countdown(secs),
         }
     }
 
-    fn render(&self, stdout: &mut Stdout) {
-        let Size(w, h) = self.logic.field_size;
+    /// Go on with the saved game, paused so that the player can get ready.
+    fn resume(&mut self, saved: SavedGame) {
+        self.logic = saved.logic;
+        self.time = Stopwatch::stopped_at(saved.elapsed);
+        self.level = saved.level;
+        self.eaten = saved.eaten;
+        self.eaten_before.clear();
+        self.state = GameState::Paused;
+    }
+
+    /// Set up a game and start recording it.
+    fn new_game(args: &Args) -> (SnakeGameLogic, Replay) {
+        let seed = args.seed.unwrap_or_else(rand::random);
+        (Self::new_logic(args, seed, 0), Replay::new(args, seed))
+    }
 
-        let mut char_matrix = Vec::new();
+    /// Start the recorded game to play along, if any.
+    fn new_ghost(args: &Args) -> Option<Ghost> {
+        let replay = args.ghost.clone()?;
+        let mut ghost_args = args.clone();
+        replay.apply(&mut ghost_args);
+        let logic = Self::new_logic(&ghost_args, replay.seed, 0);
+        Some(Ghost::new(replay, logic))
+    }
 
-        // wall
-        let mut wall_v = vec!['-'; w as usize];
-        wall_v[0] = '+';
-        wall_v[w as usize - 1] = '+';
+    /// Set up the field of the level, or the plain one if there are no levels.
+    fn new_logic(args: &Args, seed: u64, level: usize) -> SnakeGameLogic {
+        let mut logic = match args.levels.get(level) {
+            Some(level) => SnakeGameLogic::from_level(level, args.feeds, seed, args.length),
+            None if args.no_walls => {
+                SnakeGameLogic::without_walls(args.field_size, args.feeds, seed, args.length)
+            }
+            None => SnakeGameLogic::with_start(
+                args.field_size,
+                args.feeds,
+                seed,
+                args.length,
+                args.start(seed),
+            ),
+        };
+        if args.two_player {
+            logic.add_snake();
+        }
+        logic.set_wrap_x(args.wrap || args.wrap_x);
+        logic.set_wrap_y(args.wrap || args.wrap_y);
+        logic.set_zen(args.zen);
+        logic.set_poison(args.poison);
+        logic.set_reverse(args.reverse);
+        logic.set_star(args.star);
+        logic.set_slow(args.slow);
+        logic.set_golden(args.golden);
+        if let Some(weights) = args.feed_weights {
+            logic.set_feed_weights(weights);
+        }
+        logic.set_feed_ttl(args.feed_ttl);
+        logic.set_feed_drift(args.feed_drift);
+        logic.set_bomb_interval(args.bombs);
+        logic.set_avoid_head(args.avoid_head);
+        logic.set_lives(args.lives);
+        logic.set_grace(args.grace);
+        logic.set_growth_per_food(args.growth);
+        logic.set_combo_window(args.combo);
+        if args.practice {
+            logic.set_history_len(PRACTICE_HISTORY_LEN);
+        }
+        // The obstacles of the difficulty are left off a field without walls as well.
+        if !args.no_walls {
+            logic.place_obstacles(args.obstacles);
+        }
+        logic.place_portals(args.portals);
+        logic
+    }
 
-        let mut wall_h = vec![' '; w as usize];
-        wall_h[0] = '|';
-        wall_h[w as usize - 1] = '|';
+    /// Go to the next level if the score is enough for it, keeping the score.
+    fn advance_level(&mut self) {
+        let next = self.level + 1;
+        if next >= self.args.levels.len() || self.logic.score() < self.args.level_score * next {
+            return;
+        }
+        let score = self.logic.score();
+        // Each level has its own seed, so that a replay makes the same game.
+        let seed = self.record.seed.wrapping_add(next as u64);
+        self.logic = Self::new_logic(&self.args, seed, next);
+        self.logic.set_score(score);
+        self.level = next;
+        self.trail.clear();
+        self.eaten_before.clear();
+    }
 
-        char_matrix.push(wall_v.clone());
-        for _ in 0..h - 2 {
-            char_matrix.push(wall_h.clone());
+    fn scene(&self) -> Scene<'_> {
+        Scene {
+            logic: &self.logic,
+            state: self.state,
+            high_score: self.high_score.get(),
+            elapsed: self.time.elapsed(),
+            time_left: self.time_left(),
+            level: (!self.args.levels.is_empty()).then_some(self.level + 1),
+            speed: self.speed,
+            difficulty: self.args.difficulty,
+            daily: self.args.daily.as_deref(),
+            help: self.help.then_some(self.help_line.as_str()),
+            prompts: &self.prompts,
+            quitting: self.quitting.is_some(),
+            leaderboard: self.shows_leaderboard().then(|| self.leaderboard.entries()),
+            name: self.name.as_deref(),
+            panel: self.panel.map(|_| self.panel_lines()),
+            flash: false,
+            gradient: self.args.gradient,
+            ghost: self.ghost.as_ref().and_then(Ghost::head),
+            trail: &self.trail,
         }
-        char_matrix.push(wall_v);
+    }
 
-        // head & body
-        let mut body = self.logic.body.iter();
-        let head_pos = body.next().unwrap();
-        let head_char = match self.logic.dir {
-            Direction::Up => '^',
-            Direction::Down => 'v',
-            Direction::Left => '<',
-            Direction::Right => '>',
+    /// The leaderboard is shown after a game which can get on it.
+    fn shows_leaderboard(&self) -> bool {
+        matches!(self.state, GameState::Over(_) | GameState::TimeUp)
+            && !self.args.two_player
+            && !self.args.practice
+            && !self.args.zen
+    }
+
+    /// The controls and the settings of the game.
+    fn panel_lines(&self) -> Vec<String> {
+        let mode = if self.args.replay.is_some() {
+            "replay"
+        } else if self.args.ai {
+            "autopilot"
+        } else if self.args.two_player {
+            "two players"
+        } else if self.args.practice {
+            "practice"
+        } else if self.args.zen {
+            "zen"
+        } else if self.args.time_limit.is_some() {
+            "time attack"
+        } else {
+            "normal"
         };
-        char_matrix[head_pos.1 as usize][head_pos.0 as usize] = head_char;
-        body.for_each(|p| char_matrix[p.1 as usize][p.0 as usize] = 'x');
+        let mut lines = vec!["CONTROLS".to_string(), String::new()];
+        lines.extend(self.controls.iter().cloned());
+        lines.push(String::new());
+        lines.push(format!("speed: {}%  mode: {}", self.speed, mode));
+        if let Some(difficulty) = self.args.difficulty {
+            lines.push(format!("difficulty: {}", difficulty));
+        }
+        lines.push(String::new());
+        lines.push("any key: close".to_string());
+        lines
+    }
+
+    /// Remaining time of the time attack.
+    fn time_left(&self) -> Option<Duration> {
+        let limit = self.args.time_limit?;
+        Some(limit.saturating_sub(self.time.elapsed()))
+    }
+
+    /// Interval until the next move.
+    fn tick_interval(&self) -> Duration {
+        const STEP: Duration = Duration::from_millis(5);
+        const FLOOR: Duration = Duration::from_millis(60);
+
+        match self.state {
+            GameState::Countdown(0) => return COUNTDOWN_STEP / 2,
+            GameState::Countdown(_) => return COUNTDOWN_STEP,
+            _ => {}
+        }
+        let mut tick = self.args.tick * 100 / self.speed;
+        if self.args.accelerate {
+            let floor = FLOOR.min(tick);
+            let eaten = u32::try_from(self.eaten).unwrap_or(u32::MAX);
+            tick = tick.saturating_sub(STEP.saturating_mul(eaten)).max(floor);
+        }
+        // Slow motion makes the moves twice as long.
+        if self.logic.slow_left() > 0 {
+            tick *= 2;
+        }
+        tick
+    }
+
+    /// Start a new game on the same field with the same settings.
+    fn restart(&mut self) {
+        (self.logic, self.record) = Self::new_game(&self.args);
+        self.ghost = Self::new_ghost(&self.args);
+        self.trail.clear();
+        self.level = 0;
+        self.eaten = 0;
+        self.eaten_before.clear();
+        self.time = Stopwatch::start();
+        self.set_state(Self::start_state(&self.args));
+    }
 
-        // feed
-        char_matrix[self.logic.pos_feed.1 as usize][self.logic.pos_feed.0 as usize] = '@';
+    /// Change the state, and keep the time only while playing.
+    fn set_state(&mut self, state: GameState) {
+        self.state = state;
+        if state == GameState::Playing {
+            self.time.resume();
+        } else {
+            self.time.stop();
+        }
+    }
+
+    /// End the game, and save the score and the recording.
+    /// A replay only checks the score against the recording.
+    fn end(&mut self, state: GameState) -> io::Result<()> {
+        if let Some(replay) = &self.args.replay {
+            self.set_state(GameState::ReplayEnd(replay.score));
+            return Ok(());
+        }
+        self.set_state(state);
+        // A two-player game, a practice or a zen game is not comparable to a normal game.
+        if !self.args.two_player && !self.args.practice && !self.args.zen {
+            self.high_score.update(self.logic.score());
+            if self.leaderboard.qualifies(self.logic.score()) {
+                self.name = Some(String::new());
+            }
+            let time = self.time.elapsed();
+            self.stats.record(self.logic.score(), self.eaten, time);
+        }
+        self.save_record()
+    }
+
+    fn save_record(&mut self) -> io::Result<()> {
+        self.record.score = self.logic.score();
+        match &self.args.record {
+            Some(path) => self.record.save(path),
+            None => Ok(()),
+        }
+    }
 
-        // to string
-        let s = char_matrix.iter().fold(String::new(), |acc, v| {
-            let s: String = v.iter().collect();
-            acc + &s + "\r\n"
+    /// Steer the snake of the player, and record it.
+    fn turn(&mut self, player: usize, d: Direction) {
+        self.logic.set_dir_of(player, d);
+        self.record.turns.push(Turn {
+            step: self.record.steps,
+            player,
+            dir: d,
         });
+    }
 
-        // output
-        write!(
-            stdout,
-            "{}score: {}{}{}",
-            termion::cursor::Goto(1, 1),
-            self.logic.score(),
-            termion::cursor::Goto(1, 2),
-            s
-        )
-        .unwrap();
-        stdout.flush().unwrap();
+    /// How the game ends with the results of the moves, if it does.
+    /// In a two-player game, a snake which crashes is out and the other one wins.
+    fn outcome(&self, results: &[MoveResult]) -> Option<GameState> {
+        let crashed = |r: &MoveResult| r.is_over() && *r != MoveResult::Won;
+        match results {
+            [result] => result.is_over().then_some(GameState::Over(*result)),
+            [first, second] => match (crashed(first), crashed(second)) {
+                (true, true) => Some(GameState::Winner(None)),
+                (true, false) => Some(GameState::Winner(Some(1))),
+                (false, true) => Some(GameState::Winner(Some(0))),
+                (false, false) => (*first == MoveResult::Won).then(|| self.leader()),
+            },
+            _ => unreachable!("at most two players"),
+        }
+    }
+
+    /// The player with the higher score wins when neither has crashed.
+    fn leader(&self) -> GameState {
+        let (first, second) = (self.logic.score_of(0), self.logic.score_of(1));
+        GameState::Winner(match first.cmp(&second) {
+            Ordering::Greater => Some(0),
+            Ordering::Less => Some(1),
+            Ordering::Equal => None,
+        })
     }
 
-    fn run(mut self) {
-        let stdin = stdin();
-        let mut stdout = stdout().into_raw_mode().unwrap();
+    /// Move the snakes by one step, and draw the result.
+    fn step(&mut self, renderer: &mut impl Renderer) -> io::Result<()> {
+        if let Some(replay) = &self.args.replay {
+            for (player, d) in replay.turns_at(self.record.steps) {
+                self.logic.set_dir_of(player, d);
+            }
+        } else if self.args.ai {
+            let d = autopilot(&self.logic);
+            if d != *self.logic.get_dir() {
+                self.turn(0, d);
+            }
+        }
+        // The cells of the snakes before the move, to find the ones the tails leave.
+        let before: Vec<Coord> = if self.args.trail {
+            self.bodies().copied().collect()
+        } else {
+            Vec::new()
+        };
+        let results = self.logic.step_each();
+        self.record.steps += 1;
+        if self.args.trail {
+            // Moves a cell stays in the trail for, fading out.
+            const TRAIL_STEPS: usize = 3;
 
-        // initialize stdout
-        write!(stdout, "{}{}", termion::clear::All, termion::cursor::Hide).unwrap();
+            let bodies: HashSet<Coord> = self.bodies().copied().collect();
+            self.trail.retain_mut(|(p, age)| {
+                *age += 1;
+                *age < TRAIL_STEPS && !bodies.contains(p)
+            });
+            let left = before.into_iter().filter(|p| !bodies.contains(p));
+            self.trail.extend(left.map(|p| (p, 0)));
+        }
+        if let Some(ghost) = &mut self.ghost {
+            ghost.step();
+        }
+        let eaten = (results.iter())
+            .filter(|r| matches!(r, MoveResult::Ate(Food::Feed | Food::Golden)))
+            .count();
+        if self.args.practice {
+            if self.eaten_before.len() == PRACTICE_HISTORY_LEN {
+                self.eaten_before.pop_front();
+            }
+            self.eaten_before.push_back(self.eaten);
+        }
+        self.eaten += eaten as u64;
+        if self.args.sound && eaten > 0 {
+            renderer.bell()?;
+        }
+        let replay_end = (self.args.replay.as_ref())
+            .filter(|replay| replay.steps == self.record.steps)
+            .map(|replay| replay.score);
+        if let Some(state) = self.outcome(&results) {
+            if state != GameState::Over(MoveResult::Won) {
+                renderer.flash(&self.scene())?;
+            }
+            self.end(state)?;
+        } else if self.time_left() == Some(Duration::ZERO) {
+            let state = if self.args.two_player {
+                self.leader()
+            } else {
+                GameState::TimeUp
+            };
+            self.end(state)?;
+        } else if let Some(score) = replay_end {
+            self.set_state(GameState::ReplayEnd(score));
+        } else {
+            self.advance_level();
+        }
+        renderer.draw(&self.scene())
+    }
 
-        self.render(&mut stdout);
+    /// Take back the last step, with the feeds eaten in it.
+    /// Return false if there is no step to undo.
+    fn undo(&mut self) -> bool {
+        if !self.logic.step_back() {
+            return false;
+        }
+        self.record.steps -= 1;
+        if let Some(eaten) = self.eaten_before.pop_back() {
+            self.eaten = eaten;
+        }
+        self.trail.clear();
+        self.rewind_ghost();
+        true
+    }
 
-        // render signal
-        let tx = self.event_tx.clone();
-        thread::spawn(move || loop {
-            thread::sleep(Duration::from_millis(150));
-            if tx.send(SnakeGameEvent::Render).is_err() {
-                break;
+    /// Take the ghost back to where it was after the steps of the current game, such as after an undo.
+    fn rewind_ghost(&mut self) {
+        self.ghost = Self::new_ghost(&self.args);
+        if let Some(ghost) = &mut self.ghost {
+            for _ in 0..self.record.steps {
+                ghost.step();
             }
-        });
+        }
+    }
 
-        let tx = self.event_tx.clone();
-        thread::spawn(move || {
-            for c in stdin.keys() {
-                let event = c.unwrap();
-                use SnakeGameEvent::*;
-
-                let msg = match event {
-                    Key::Char('h') => Some(ChangeDir(Direction::Left)),
-                    Key::Char('j') => Some(ChangeDir(Direction::Down)),
-                    Key::Char('k') => Some(ChangeDir(Direction::Up)),
-                    Key::Char('l') => Some(ChangeDir(Direction::Right)),
-                    Key::Char('q') => Some(Quit),
-                    _ => None,
-                };
-
-                if let Some(msg) = msg {
-                    if tx.send(msg).is_err() {
-                        break;
+    /// Cells of every snake.
+    fn bodies(&self) -> impl Iterator<Item = &Coord> {
+        (0..self.logic.n_snakes()).flat_map(|player| self.logic.body_of(player))
+    }
+
+    /// Play until the player quits or the input ends, and return how the last game ended.
+    /// Everything runs on the calling thread, waiting for the input until the next move.
+    fn run(
+        mut self,
+        renderer: &mut impl Renderer,
+        mut input: impl InputSource,
+    ) -> io::Result<Outcome> {
+        renderer.draw(&self.scene())?;
+
+        // The interval can change during the game, so the loop itself keeps the time.
+        // The frames are drawn on their own cadence between the moves.
+        let mut next_tick = Instant::now() + self.tick_interval();
+        let mut next_frame = Instant::now() + FRAME_INTERVAL;
+        loop {
+            // Nothing moves unless playing, so sleep until the player does something instead of waking up at every tick.
+            let due = match self.state {
+                GameState::Playing => Some(next_tick.min(next_frame)),
+                GameState::Countdown(_) => Some(next_tick),
+                _ => None,
+            };
+            let timeout = due.map(|due| due.saturating_duration_since(Instant::now()));
+            input.set_text_mode(self.name.is_some());
+            let e = match input.poll_event(timeout) {
+                Input::Event(e) => {
+                    if timeout.is_none() {
+                        // The next move is a whole interval after the game is resumed.
+                        next_tick = Instant::now() + self.tick_interval();
                     }
+                    e
                 }
-            }
-        });
+                Input::Timeout => {
+                    let now = Instant::now();
+                    // A move draws the frame as well.
+                    next_frame = now + FRAME_INTERVAL;
+                    if now < next_tick {
+                        SnakeGameEvent::Redraw
+                    } else {
+                        next_tick = now + self.tick_interval();
+                        SnakeGameEvent::Tick
+                    }
+                }
+                Input::End => break,
+            };
 
-        let mut dir = *self.logic.get_dir();
-        while let Ok(e) = self.event_rx.recv() {
             use SnakeGameEvent::*;
+            if let Some(name) = &mut self.name {
+                match e {
+                    Typed(c) if !c.is_control() && name.chars().count() < MAX_NAME_LEN => {
+                        name.push(c)
+                    }
+                    Erase => {
+                        name.pop();
+                    }
+                    Select => {
+                        let name = self.name.take().unwrap();
+                        let name = if name.trim().is_empty() {
+                            "???".to_string()
+                        } else {
+                            name
+                        };
+                        self.leaderboard.insert(name, self.logic.score());
+                    }
+                    // The score is left off the leaderboard.
+                    Quit => self.name = None,
+                    Resize(..) => {}
+                    _ => continue,
+                }
+                renderer.draw(&self.scene())?;
+                continue;
+            }
+            if let Some(state) = self.panel {
+                match e {
+                    Tick | Redraw | Resize(..) => {}
+                    // Any key closes the panel.
+                    _ => {
+                        self.panel = None;
+                        self.set_state(state);
+                        renderer.draw(&self.scene())?;
+                        continue;
+                    }
+                }
+            }
+            if let Some(state) = self.quitting {
+                match e {
+                    Yes => break,
+                    Tick | Redraw | Resize(..) => {}
+                    // Any other key goes back to the game.
+                    _ => {
+                        self.quitting = None;
+                        self.set_state(state);
+                        renderer.draw(&self.scene())?;
+                        continue;
+                    }
+                }
+            }
             match e {
                 ChangeDir(d) => {
-                    dir = d;
+                    // The recording or the autopilot steers the snake instead.
+                    if self.args.replay.is_some() || self.args.ai {
+                        continue;
+                    }
+                    self.turn(0, d);
                 }
-                Render => {
-                    if !self.logic.r#move(dir) {
-                        break;
+                ChangeDir2(d) => {
+                    if self.args.replay.is_some() || !self.args.two_player {
+                        continue;
+                    }
+                    self.turn(1, d);
+                }
+                Tick => match self.state {
+                    GameState::Countdown(0) => {
+                        self.set_state(GameState::Playing);
+                        next_tick = Instant::now() + self.tick_interval();
+                        self.step(renderer)?;
+                    }
+                    GameState::Countdown(secs) => {
+                        self.set_state(GameState::Countdown(secs - 1));
+                        next_tick = Instant::now() + self.tick_interval();
+                        renderer.draw(&self.scene())?;
+                    }
+                    GameState::Playing => self.step(renderer)?,
+                    _ => continue,
+                },
+                Redraw => {
+                    if self.state != GameState::Playing {
+                        continue;
+                    }
+                    renderer.draw(&self.scene())?;
+                }
+                StepOnce => {
+                    // Move one step at a time while paused, to look into the movement.
+                    if self.state != GameState::Paused {
+                        continue;
+                    }
+                    self.step(renderer)?;
+                }
+                Undo => {
+                    // Undo while paused, or to take back the crash.
+                    if !matches!(
+                        self.state,
+                        GameState::Paused | GameState::Over(_) | GameState::Winner(_)
+                    ) || !self.undo()
+                    {
+                        continue;
+                    }
+                    self.set_state(GameState::Paused);
+                    renderer.draw(&self.scene())?;
+                }
+                ToggleHelp => {
+                    self.help = !self.help;
+                    renderer.draw(&self.scene())?;
+                }
+                ShowControls => {
+                    // Nothing moves while the panel is shown.
+                    self.panel = Some(self.state);
+                    if self.state == GameState::Playing {
+                        self.set_state(GameState::Paused);
+                    }
+                    renderer.draw(&self.scene())?;
+                }
+                Slower => {
+                    self.speed = self.speed.saturating_sub(SPEED_STEP).max(MIN_SPEED);
+                    renderer.draw(&self.scene())?;
+                }
+                Faster => {
+                    self.speed = (self.speed + SPEED_STEP).min(MAX_SPEED);
+                    renderer.draw(&self.scene())?;
+                }
+                TogglePause => {
+                    let state = match self.state {
+                        GameState::Playing => GameState::Paused,
+                        GameState::Paused => GameState::Playing,
+                        // Skip the countdown.
+                        GameState::Countdown(_) => GameState::Playing,
+                        _ => continue,
+                    };
+                    self.set_state(state);
+                    next_tick = Instant::now() + self.tick_interval();
+                    renderer.draw(&self.scene())?;
+                }
+                Select => {
+                    // Skip the countdown.
+                    if !matches!(self.state, GameState::Countdown(_)) {
+                        continue;
+                    }
+                    self.set_state(GameState::Playing);
+                    next_tick = Instant::now() + self.tick_interval();
+                    renderer.draw(&self.scene())?;
+                }
+                Restart => {
+                    if !self.state.is_over() {
+                        continue;
+                    }
+                    self.restart();
+                    next_tick = Instant::now() + self.tick_interval();
+                    renderer.draw(&self.scene())?;
+                }
+                Resize(cols, rows) => {
+                    // Stop the game while the frame does not fit in the terminal.
+                    let fits = check_terminal_size(self.scene().frame_size(), (cols, rows));
+                    let running =
+                        matches!(self.state, GameState::Playing | GameState::Countdown(_));
+                    if running && fits.is_err() {
+                        self.set_state(GameState::Paused);
                     }
-                    self.render(&mut stdout);
+                    renderer.draw(&self.scene())?;
                 }
                 Quit => {
-                    break;
+                    // Ask first not to throw away a game by mistake.
+                    if self.state.is_over() {
+                        break;
+                    }
+                    self.quitting = Some(self.state);
+                    self.set_state(GameState::Paused);
+                    renderer.draw(&self.scene())?;
+                }
+                Yes | No | Typed(_) | Erase => {}
+            }
+        }
+
+        // Keep the game quit on the way as well.
+        if self.args.replay.is_none() && !self.state.is_over() {
+            self.save_record()?;
+            // A two-player game or a practice cannot be resumed.
+            if !self.args.two_player && !self.args.practice {
+                let elapsed = self.time.elapsed();
+                let saved = SavedGame::new(&self.logic, elapsed, self.level, self.eaten);
+                self.save.save(&saved)?;
+            }
+        }
+        Ok(Outcome::of(self.state))
+    }
+}
+
+#[derive(Clone)]
+struct Args {
+    field_size: Size,
+    wrap: bool,
+    /// Go through the left and right walls only.
+    wrap_x: bool,
+    /// Go through the top and bottom walls only.
+    wrap_y: bool,
+    color: bool,
+    /// Draw the walls with box-drawing characters.
+    unicode: bool,
+    /// Shift the hue of the bodies along the snakes.
+    gradient: bool,
+    /// Show the cells the tails have just left.
+    trail: bool,
+    /// Ring the bell when the snake eats.
+    sound: bool,
+    tick: Duration,
+    accelerate: bool,
+    /// Seconds to count down before a game starts. There is no countdown if 0.
+    countdown: u32,
+    /// Number of obstacles put inside the field.
+    obstacles: usize,
+    /// Number of pairs of portals put inside the field, besides the ones of the levels.
+    portals: usize,
+    /// Number of feeds on the field at the same time.
+    feeds: usize,
+    poison: bool,
+    golden: bool,
+    /// Keep a feed on the field which reverses the controls.
+    reverse: bool,
+    /// Let a star appear rarely, which makes the snake go through its body.
+    star: bool,
+    /// Let an item appear sometimes, which slows the game down.
+    slow: bool,
+    /// Chances of the kinds of a new feed, instead of the ones of `--golden`.
+    feed_weights: Option<FeedWeights>,
+    /// Never put a new feed right in front of the head.
+    avoid_head: bool,
+    /// Seed of the random number generator. A random one is used if not given.
+    seed: Option<u64>,
+    /// Date of the daily challenge being played, which the seed is made from.
+    daily: Option<String>,
+    /// Time limit of the time attack. The game has no limit if not given.
+    time_limit: Option<Duration>,
+    /// File to save the recording of each game to.
+    record: Option<PathBuf>,
+    /// File to append every frame drawn to.
+    record_frames: Option<PathBuf>,
+    /// File to write the frames to as an asciicast.
+    export_cast: Option<PathBuf>,
+    /// Recording to play again instead of playing a new game.
+    replay: Option<Replay>,
+    /// Recording whose snake is shown along with the game.
+    ghost: Option<Replay>,
+    /// Let the autopilot play.
+    ai: bool,
+    /// Two players play on the same keyboard.
+    two_player: bool,
+    /// Length of the snake at the start.
+    length: usize,
+    /// Cell and direction of the head at the start, instead of the default ones.
+    start: Option<(Coord, Direction)>,
+    /// Cell of the first feed, instead of the center.
+    feed_at: Option<Coord>,
+    /// Start from a random place, which the seed decides.
+    random_start: bool,
+    /// Number of moves until the feeds move if not eaten. They stay if not given.
+    feed_ttl: Option<usize>,
+    /// Number of moves between the drifts of the feeds to next cells. They stay still if not given.
+    feed_drift: Option<usize>,
+    /// Number of moves between new bombs. No bombs appear if not given.
+    bombs: Option<usize>,
+    /// Number of crashes the snake survives, plus one.
+    lives: usize,
+    /// Steps at the start in which a crash does not count.
+    grace: usize,
+    /// Segments the snake grows by for a feed.
+    growth: usize,
+    /// Moves within which a feed has to be eaten after the previous one to multiply the points. There are no combos if not given.
+    combo: Option<usize>,
+    /// Levels to play in order. The field is the first one if any.
+    levels: Vec<Level>,
+    /// Points to make in each level to go to the next one.
+    level_score: usize,
+    /// Let the player undo steps. The score does not count.
+    practice: bool,
+    /// Never end the game by a crash. The score does not count.
+    zen: bool,
+    /// Play on a field without walls, showing the window around the head of the field size.
+    no_walls: bool,
+    /// Show the controls below the field.
+    help: bool,
+    /// The preset chosen with `--difficulty`, if any.
+    difficulty: Option<Difficulty>,
+    /// Show the menu before the game.
+    menu: bool,
+    /// Print the statistics instead of playing.
+    stats: bool,
+    /// Go on with the game saved when the player quit last.
+    resume: bool,
+}
+
+impl Args {
+    /// Take the options of the command line, on top of the settings in the config file.
+    fn new(cli: Cli, config: &Config) -> Result<Self, String> {
+        let Cli {
+            difficulty,
+            width,
+            height,
+            fit,
+            wrap,
+            wrap_x,
+            wrap_y,
+            no_color,
+            unicode,
+            no_gradient,
+            trail,
+            sound,
+            no_sound,
+            tick_ms,
+            accelerate,
+            countdown,
+            length,
+            start,
+            feed_at,
+            random_start,
+            obstacles,
+            portals,
+            feeds,
+            feed_ttl,
+            moving_food: feed_drift,
+            bombs,
+            lives,
+            grace,
+            growth,
+            combo,
+            level,
+            level_score,
+            poison,
+            golden,
+            reverse,
+            star,
+            slow_mo: slow,
+            feed_weights,
+            avoid_head,
+            mut seed,
+            daily,
+            time_attack,
+            record,
+            record_frames,
+            export_cast,
+            replay,
+            ghost,
+            ai,
+            two_player,
+            practice,
+            zen,
+            no_walls,
+            no_help,
+            no_menu,
+            stats,
+            resume,
+        } = cli;
+        let mut w = match width {
+            Some(w) => w,
+            None => config
+                .width
+                .map_or(Ok(20), |w| Self::check_len("width", w))?,
+        };
+        let mut h = match height {
+            Some(h) => h,
+            None => config
+                .height
+                .map_or(Ok(20), |h| Self::check_len("height", h))?,
+        };
+        // These are left to the difficulty if not given.
+        let wrap = if wrap { Some(true) } else { config.wrap };
+        let tick = match tick_ms.or(config.tick_ms) {
+            Some(0) => return Err("`tick_ms` must be positive".to_string()),
+            Some(ms) => Some(Duration::from_millis(ms)),
+            None => None,
+        };
+        let unicode = unicode || config.unicode.unwrap_or(false);
+        let sound = (sound || config.sound.unwrap_or(false)) && !no_sound;
+        // Follow https://no-color.org and do not color dumb terminals.
+        let color = env::var_os("NO_COLOR").is_none()
+            && env::var("TERM").map_or(true, |term| term != "dumb")
+            && config.color.unwrap_or(true)
+            && !no_color;
+        let time_limit = time_attack.map(Duration::from_secs);
+        let help = !no_help;
+        let menu = !no_menu;
+        let replay = replay.map(|path| Replay::load(&path)).transpose()?;
+        let ghost = match ghost {
+            Some(path) => {
+                let recording = Replay::load(&path)?;
+                // The ghost does not go to the next level.
+                if recording.levels.len() > 1 {
+                    return Err(
+                        "`--ghost` cannot follow a recording of more than one level".to_string()
+                    );
                 }
+                Some(recording)
+            }
+            None => None,
+        };
+        let levels = level
+            .iter()
+            .map(|path| Self::load_level(path))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let preset = difficulty.unwrap_or(Difficulty::Normal);
+        let wrap = wrap.unwrap_or(preset.wrap());
+        let tick = tick.unwrap_or(preset.tick());
+        // Only the snake itself ends a game without walls.
+        if no_walls && (obstacles.is_some() || bombs.is_some()) {
+            return Err("`--no-walls` cannot be used with `--obstacles` or `--bombs`".to_string());
+        }
+        let obstacles = obstacles.unwrap_or(preset.obstacles());
+
+        if ai && two_player {
+            return Err("`--ai` cannot be used with `--two-player`".to_string());
+        }
+        // Undone steps cannot be replayed.
+        if practice && (record.is_some() || replay.is_some()) {
+            return Err("`--practice` cannot be used with `--record` or `--replay`".to_string());
+        }
+        let daily = if daily {
+            if seed.is_some() || replay.is_some() {
+                return Err("`--daily` cannot be used with `--seed` or `--replay`".to_string());
+            }
+            let date = daily::today();
+            seed = Some(daily::seed(&date));
+            Some(date)
+        } else {
+            None
+        };
+        if resume && (two_player || practice || replay.is_some()) {
+            return Err(
+                "`--resume` cannot be used with `--two-player`, `--practice` or `--replay`"
+                    .to_string(),
+            );
+        }
+
+        let custom_start = start.is_some() || feed_at.is_some() || random_start;
+        if custom_start && (two_player || !levels.is_empty()) {
+            return Err(
+                "`--start`, `--feed-at` and `--random-start` cannot be used with `--two-player` or `--level`"
+                    .to_string(),
+            );
+        }
+        if no_walls && (custom_start || two_player || !levels.is_empty()) {
+            return Err(
+                "`--no-walls` cannot be used with `--start`, `--feed-at`, `--random-start`, `--two-player` or `--level`"
+                    .to_string(),
+            );
+        }
+        if random_start && (start.is_some() || feed_at.is_some()) {
+            return Err(
+                "`--random-start` cannot be used with `--start` or `--feed-at`".to_string(),
+            );
+        }
+
+        if let Some(level) = levels.first() {
+            if two_player {
+                return Err("`--two-player` cannot be used with `--level`".to_string());
+            }
+            Size(w, h) = level.size();
+            let max = levels.iter().map(Level::max_initial_len).min().unwrap();
+            if length == 0 || length > max {
+                return Err(format!(
+                    "`--length` must be from 1 to {} for the levels (got {})",
+                    max, length
+                ));
+            }
+        } else {
+            if fit {
+                let terminal = terminal::size()
+                    .map_err(|e| format!("failed to get the size of the terminal: {}", e))?;
+                let help_line = Keymap::from_config(&config.keys)?.help_line();
+                Size(w, h) = Self::terminal_field_size(terminal, help.then_some(&help_line))?;
+            }
+            if length == 0 || length > max_initial_len(w) {
+                return Err(format!(
+                    "`--length` must be from 1 to {} for the width {} (got {})",
+                    max_initial_len(w),
+                    w,
+                    length
+                ));
             }
         }
 
-        // finalize stdout
-        write!(stdout, "{}", termion::cursor::Show).unwrap();
+        let mut args = Self {
+            field_size: Size(w, h),
+            wrap,
+            wrap_x,
+            wrap_y,
+            color,
+            unicode,
+            gradient: !no_gradient,
+            trail,
+            sound,
+            tick,
+            accelerate,
+            countdown,
+            obstacles,
+            portals,
+            feeds,
+            poison,
+            golden,
+            reverse,
+            star,
+            slow,
+            feed_weights,
+            avoid_head,
+            seed,
+            daily,
+            time_limit,
+            record,
+            record_frames,
+            export_cast,
+            replay: None,
+            ghost,
+            ai,
+            two_player,
+            length,
+            start,
+            feed_at,
+            random_start,
+            feed_ttl,
+            feed_drift,
+            bombs,
+            lives,
+            grace,
+            growth,
+            combo,
+            levels,
+            level_score,
+            practice,
+            zen,
+            no_walls,
+            help,
+            difficulty,
+            menu,
+            stats,
+            resume,
+        };
+        if args.start.is_some() || args.feed_at.is_some() {
+            (args.start(0).check(args.field_size, args.length))
+                .map_err(|e| format!("`--start` or `--feed-at` is invalid: {}", e))?;
+        }
+        if let Some(replay) = replay {
+            replay.apply(&mut args);
+            args.replay = Some(replay);
+        }
+        Ok(args)
+    }
+
+    /// Take the settings of the difficulty, over the ones given one by one.
+    fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.difficulty = Some(difficulty);
+        self.tick = difficulty.tick();
+        self.obstacles = difficulty.obstacles();
+        self.wrap = difficulty.wrap();
+    }
+
+    /// The largest field whose frame fits in the terminal of the columns and rows,
+    /// with the help bar if it is shown.
+    fn terminal_field_size((cols, rows): (u16, u16), help: Option<&str>) -> Result<Size, String> {
+        let smallest = render::frame_size(Size(MIN_FIELD_LEN, MIN_FIELD_LEN), help);
+        if check_terminal_size(smallest, (cols, rows)).is_err() {
+            return Err(format!(
+                "the terminal is too small: {}x{} is required",
+                smallest.0, smallest.1
+            ));
+        }
+        // The header and the help bar take the rows above and below the field.
+        let Size(_, extra_rows) = render::frame_size(Size(0, 0), help);
+        Ok(Size(cols, rows - extra_rows))
+    }
+
+    fn load_level(path: &Path) -> Result<Level, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read `{}`: {}", path.display(), e))?;
+        Level::parse(&text).map_err(|e| format!("invalid level `{}`: {}", path.display(), e))
+    }
+
+    fn check_len(name: &str, len: u16) -> Result<u16, String> {
+        if len < MIN_FIELD_LEN {
+            return Err(format!(
+                "`{}` must be at least {} (got {})",
+                name, MIN_FIELD_LEN, len
+            ));
+        }
+        Ok(len)
+    }
+
+    /// Parse the options as given after the name of the program.
+    #[cfg(test)]
+    fn parse(args: impl IntoIterator<Item = String>, config: &Config) -> Result<Self, String> {
+        let args = std::iter::once("snake-game".to_string()).chain(args);
+        let cli = Cli::try_parse_from(args).map_err(|e| e.to_string())?;
+        Self::new(cli, config)
+    }
+
+    /// Where the snake and the first feed are at the start of a game with the seed.
+    fn start(&self, seed: u64) -> Start {
+        if self.random_start {
+            return Start::random(self.field_size, self.length, seed);
+        }
+        let mut start = Start::new(self.field_size, self.length);
+        if let Some((head, dir)) = self.start {
+            start.head = head;
+            start.dir = dir;
+        }
+        if let Some(feed) = self.feed_at {
+            start.feed = feed;
+        }
+        start
     }
 }
 
 fn main() {
-    let game_ctrl = SnakeGameControler::new();
-    game_ctrl.run();
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(msg) => {
+            eprintln!("error: {}", msg);
+            process::exit(2);
+        }
+    };
+    let mut keymap = match Keymap::from_config(&config.keys) {
+        Ok(keymap) => keymap,
+        Err(msg) => {
+            eprintln!("error: {}", msg);
+            process::exit(2);
+        }
+    };
+    let args = match Args::new(Cli::parse(), &config) {
+        Ok(args) => args,
+        Err(msg) => {
+            eprintln!("error: {}", msg);
+            eprintln!("{}", Cli::command().render_usage());
+            process::exit(2);
+        }
+    };
+
+    if args.stats {
+        println!("{}", Stats::load());
+        return;
+    }
+    if args.two_player {
+        keymap.split_for_two_players();
+    }
+
+    // Warn before the terminal is taken over.
+    let saved = if args.resume {
+        match SaveFile::new().take() {
+            Ok(Some(saved)) => Some(saved),
+            Ok(None) => {
+                eprintln!("warning: no game is saved; starting a new game");
+                None
+            }
+            Err(msg) => {
+                eprintln!("warning: {}; starting a new game", msg);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let theme = if args.unicode {
+        Theme::box_drawing()
+    } else {
+        Theme::default()
+    };
+    match play(args, theme, keymap, saved) {
+        Ok(outcome) => process::exit(outcome.code()),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Play on the terminal.
+/// The terminal is restored before this returns, even on error.
+fn play(args: Args, theme: Theme, keymap: Keymap, saved: Option<SavedGame>) -> io::Result<Outcome> {
+    // The frame drawn on a smaller terminal would scroll and break, so do not even start.
+    let help = args.help.then(|| keymap.help_line());
+    if let Ok(terminal) = terminal::size() {
+        let frame = render::frame_size(args.field_size, help.as_deref());
+        check_terminal_size(frame, terminal).map_err(io::Error::other)?;
+    }
+    // A signal such as SIGTERM skips dropping the renderer, so the handler restores the terminal instead.
+    // Exit as a shell does for SIGINT.
+    if let Err(e) = ctrlc::set_handler(|| {
+        render::restore_terminal();
+        process::exit(130);
+    }) {
+        eprintln!("warning: failed to handle signals: {}", e);
+    }
+    let open_error = |path: &Path| {
+        let path = path.display().to_string();
+        move |e: io::Error| io::Error::new(e.kind(), format!("failed to open `{}`: {}", path, e))
+    };
+    let mut renderer: Box<dyn Renderer> = Box::new(TermRenderer::new(args.color, theme.clone())?);
+    if let Some(path) = &args.record_frames {
+        renderer =
+            Box::new(FileRenderer::new(path, renderer, theme.clone()).map_err(open_error(path))?);
+    }
+    if let Some(path) = &args.export_cast {
+        let size = render::frame_size(args.field_size, help.as_deref());
+        renderer = Box::new(
+            CastRenderer::new(path, size, renderer, theme, args.color).map_err(open_error(path))?,
+        );
+    }
+    play_on(args, renderer, keymap, saved)
+}
+
+/// Return an error if the terminal of the columns and rows cannot show the frame.
+fn check_terminal_size(frame: Size, (cols, rows): (u16, u16)) -> Result<(), String> {
+    let Size(w, h) = frame;
+    if cols < w || rows < h {
+        return Err(format!(
+            "the terminal is too small: need at least {}x{} (got {}x{})",
+            w, h, cols, rows
+        ));
+    }
+    Ok(())
+}
+
+/// Play the game drawn by the renderer.
+fn play_on(
+    mut args: Args,
+    mut renderer: impl Renderer,
+    keymap: Keymap,
+    saved: Option<SavedGame>,
+) -> io::Result<Outcome> {
+    let controls = keymap.describe();
+    let help_line = keymap.help_line();
+    let prompts = keymap.prompts();
+    let mut input = TermInput::new(keymap);
+
+    // A replay or a resumed game has its settings already.
+    if args.menu && args.replay.is_none() && saved.is_none() {
+        let mut menu = Menu::new(args.difficulty.unwrap_or(Difficulty::Normal));
+        if !menu.run(&mut renderer, &mut input)? {
+            return Ok(Outcome::Quit);
+        }
+        // Keep the settings given one by one unless another difficulty is chosen.
+        if menu.difficulty != args.difficulty.unwrap_or(Difficulty::Normal) {
+            args.set_difficulty(menu.difficulty);
+        }
+    }
+
+    let mut game_ctrl = SnakeGameControler::new(args);
+    game_ctrl.controls = controls;
+    game_ctrl.help_line = help_line;
+    game_ctrl.prompts = prompts;
+    if let Some(saved) = saved {
+        game_ctrl.resume(saved);
+    }
+    game_ctrl.run(&mut renderer, input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Record the head and the state of every scene.
+    #[derive(Default)]
+    struct Recorder(Vec<(Coord, GameState)>);
+
+    impl Renderer for Recorder {
+        fn draw(&mut self, scene: &Scene) -> io::Result<()> {
+            let head = *scene.logic.body().next().unwrap();
+            self.0.push((head, scene.state));
+            Ok(())
+        }
+    }
+
+    fn new_controler() -> SnakeGameControler {
+        let mut args = Args::parse(std::iter::empty(), &Config::default()).unwrap();
+        args.seed = Some(0);
+        // Never step by the timer, so only the script moves the snake.
+        args.tick = Duration::from_secs(3600);
+        args.countdown = 0;
+        let mut ctrl = SnakeGameControler::new(args);
+        ctrl.high_score = HighScore {
+            path: None,
+            value: 0,
+        };
+        ctrl.stats = Stats::default();
+        ctrl.leaderboard = Leaderboard::default();
+        ctrl.save = SaveFile::default();
+        ctrl
+    }
+
+    #[test]
+    fn command_line_overrides_config() {
+        let config = Config {
+            width: Some(40),
+            height: Some(15),
+            wrap: Some(true),
+            ..Config::default()
+        };
+        let args = ["--width", "30"].map(String::from).into_iter();
+        let args = Args::parse(args, &config).unwrap();
+        assert_eq!(args.field_size, Size(30, 15));
+        assert!(args.wrap);
+
+        let config = Config {
+            tick_ms: Some(0),
+            ..Config::default()
+        };
+        assert!(Args::parse(std::iter::empty(), &config).is_err());
+
+        // The snake has to fit in the default width of 20.
+        let args = |len: &str| ["--length", len].map(String::from).into_iter();
+        assert_eq!(
+            Args::parse(args("17"), &Config::default()).unwrap().length,
+            17
+        );
+        assert!(Args::parse(args("18"), &Config::default()).is_err());
+    }
+
+    #[test]
+    fn difficulty_sets_defaults() {
+        let parse = |args: &[&str]| {
+            let args = args.iter().map(|s| s.to_string());
+            Args::parse(args, &Config::default())
+        };
+        let args = parse(&["--difficulty", "hard"]).unwrap();
+        assert_eq!(args.difficulty, Some(Difficulty::Hard));
+        assert_eq!(args.tick, Duration::from_millis(80));
+        assert_eq!(args.obstacles, 15);
+        assert!(!args.wrap);
+
+        // Each option still wins over the preset, wherever it is.
+        let args = parse(&["--obstacles", "1", "--difficulty", "easy"]).unwrap();
+        assert_eq!(args.obstacles, 1);
+        assert!(args.wrap);
+
+        assert!(parse(&["--difficulty", "insane"]).is_err());
+    }
+
+    #[test]
+    fn start_from_options() {
+        let parse = |args: &[&str]| {
+            let args = args.iter().map(|s| s.to_string());
+            Args::parse(args, &Config::default())
+        };
+        let args = parse(&["--start", "10,10,up", "--feed-at", "3,4"]).unwrap();
+        let logic = SnakeGameControler::new_logic(&args, 0, 0);
+        assert_eq!(logic.body().next(), Some(&Coord(10, 10)));
+        assert_eq!(logic.direction(), Direction::Up);
+        assert_eq!(logic.feed(), Some(Coord(3, 4)));
+
+        // The body would go into the bottom wall.
+        assert!(parse(&["--start", "10,17,up"]).is_err());
+        assert!(parse(&["--start", "10,10,north"]).is_err());
+        assert!(parse(&["--random-start", "--two-player"]).is_err());
+        assert!(parse(&["--no-walls", "--bombs", "10"]).is_err());
+        let args = parse(&["--no-walls", "--difficulty", "hard"]).unwrap();
+        let logic = SnakeGameControler::new_logic(&args, 0, 0);
+        assert_eq!(logic.obstacles().count(), 0);
+    }
+
+    #[test]
+    fn fitted_field_fits_terminal() {
+        let help = Keymap::default().help_line();
+        for help in [None, Some(help.as_str())] {
+            for terminal in [(80, 24), (200, 60), (70, 8)] {
+                let field = Args::terminal_field_size(terminal, help).unwrap();
+                let frame = render::frame_size(field, help);
+                assert_eq!(check_terminal_size(frame, terminal), Ok(()));
+            }
+        }
+        assert_eq!(
+            Args::terminal_field_size((80, 7), Some(&help)),
+            Err("the terminal is too small: 70x8 is required".to_string())
+        );
+        assert!(Args::terminal_field_size((80, 7), None).is_ok());
+
+        // The frame drawn is as large as the one checked at the start.
+        for no_walls in [false, true] {
+            let mut ctrl = new_controler();
+            if no_walls {
+                ctrl.args.no_walls = true;
+                ctrl.restart();
+            }
+            ctrl.help_line = help.clone();
+            assert_eq!(
+                ctrl.scene().frame_size(),
+                render::frame_size(ctrl.args.field_size, Some(&help))
+            );
+        }
+    }
+
+    #[test]
+    fn terminal_must_fit_frame() {
+        let frame = render::frame_size(Size(40, 15), None);
+        assert_eq!(frame, Size(40, 16));
+        assert_eq!(check_terminal_size(frame, (40, 16)), Ok(()));
+        assert_eq!(
+            check_terminal_size(frame, (80, 15)),
+            Err("the terminal is too small: need at least 40x16 (got 80x15)".to_string())
+        );
+        // The help bar is wider than the field.
+        let help = Keymap::default().help_line();
+        let frame = render::frame_size(Size(40, 15), Some(&help));
+        assert_eq!(frame, Size(70, 17));
+    }
+
+    #[test]
+    fn time_attack_ends_when_time_runs_out() {
+        use SnakeGameEvent::*;
+
+        let mut recorder = Recorder::default();
+        let mut ctrl = new_controler();
+        ctrl.args.time_limit = Some(Duration::ZERO);
+        ctrl.run(&mut recorder, vec![Tick, TogglePause, Restart].into_iter())
+            .unwrap();
+
+        assert_eq!(
+            recorder.0,
+            vec![
+                (Coord(4, 2), GameState::Playing),
+                (Coord(5, 2), GameState::TimeUp),
+                (Coord(4, 2), GameState::Playing),
+            ]
+        );
+    }
+
+    #[test]
+    fn renderer_error_ends_game() {
+        struct Broken;
+
+        impl Renderer for Broken {
+            fn draw(&mut self, _: &Scene) -> io::Result<()> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+        }
+
+        let script = vec![SnakeGameEvent::Tick];
+        let result = new_controler().run(&mut Broken, script.into_iter());
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn game_ends_with_input() {
+        // Without `Quit`, the game ends with the input.
+        let mut recorder = Recorder::default();
+        let script = vec![SnakeGameEvent::Tick];
+        new_controler()
+            .run(&mut recorder, script.into_iter())
+            .unwrap();
+        assert_eq!(recorder.0.len(), 2);
+    }
+
+    #[test]
+    fn replay_reproduces_recorded_game() {
+        use SnakeGameEvent::*;
+
+        let path = env::temp_dir().join(format!("snake-replay-{}.toml", process::id()));
+        let mut recorded = Recorder::default();
+        let mut ctrl = new_controler();
+        ctrl.args.record = Some(path.clone());
+        let script = vec![
+            Tick,
+            ChangeDir(Direction::Down),
+            ChangeDir(Direction::Left),
+            Tick,
+            Tick,
+            ChangeDir(Direction::Down),
+            Tick,
+        ];
+        ctrl.run(&mut recorded, script.into_iter()).unwrap();
+
+        let replay = Replay::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(replay.steps, 4);
+        assert_eq!(replay.turns.len(), 3);
+
+        let mut replayed = Recorder::default();
+        let mut ctrl = new_controler();
+        replay.apply(&mut ctrl.args);
+        ctrl.args.replay = Some(replay);
+        let script = vec![Tick, Tick, Tick, Tick];
+        ctrl.run(&mut replayed, script.into_iter()).unwrap();
+
+        let heads = |r: &Recorder| r.0.iter().map(|(head, _)| *head).collect::<Vec<_>>();
+        assert_eq!(heads(&replayed), heads(&recorded));
+        assert_eq!(replayed.0.last().unwrap().1, GameState::ReplayEnd(0));
+    }
+
+    #[test]
+    fn ghost_follows_recorded_game() {
+        use SnakeGameEvent::*;
+
+        /// Record the ghost of every scene.
+        #[derive(Default)]
+        struct Ghosts(Vec<Option<Coord>>);
+
+        impl Renderer for Ghosts {
+            fn draw(&mut self, scene: &Scene) -> io::Result<()> {
+                self.0.push(scene.ghost);
+                Ok(())
+            }
+        }
+
+        let path = env::temp_dir().join(format!("snake-ghost-{}.toml", process::id()));
+        let mut recorded = Recorder::default();
+        let mut ctrl = new_controler();
+        ctrl.args.record = Some(path.clone());
+        let script = vec![Tick, ChangeDir(Direction::Down), Tick, Tick];
+        ctrl.run(&mut recorded, script.into_iter()).unwrap();
+        let replay = Replay::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // The live snake goes straight while the ghost turns.
+        let mut ghosts = Ghosts::default();
+        let mut ctrl = new_controler();
+        ctrl.args.ghost = Some(replay.clone());
+        ctrl.ghost = SnakeGameControler::new_ghost(&ctrl.args);
+        let script = vec![Tick, Tick, Tick, Tick];
+        ctrl.run(&mut ghosts, script.into_iter()).unwrap();
+
+        let heads: Vec<Option<Coord>> = recorded.0.iter().map(|(head, _)| Some(*head)).collect();
+        assert_eq!(ghosts.0[..heads.len()], heads);
+        // It is gone after the recording ends.
+        assert_eq!(ghosts.0.last(), Some(&None));
+
+        // Undoing a step takes the ghost back as well.
+        let mut ghosts = Ghosts::default();
+        let mut ctrl = new_controler();
+        ctrl.args.practice = true;
+        ctrl.args.ghost = Some(replay);
+        ctrl.restart();
+        let script = vec![Tick, Tick, TogglePause, Undo];
+        ctrl.run(&mut ghosts, script.into_iter()).unwrap();
+        assert_eq!(ghosts.0.last(), Some(&heads[1]));
+    }
+
+    #[test]
+    fn countdown_before_first_move() {
+        use SnakeGameEvent::*;
+
+        let mut recorder = Recorder::default();
+        let mut ctrl = new_controler();
+        ctrl.args.countdown = 2;
+        ctrl.restart();
+        // The snake turns during the countdown, and moves after "Go!".
+        let script = vec![ChangeDir(Direction::Down), Tick, Tick, Tick];
+        ctrl.run(&mut recorder, script.into_iter()).unwrap();
+        assert_eq!(
+            recorder.0,
+            [
+                (Coord(4, 2), GameState::Countdown(2)),
+                (Coord(4, 2), GameState::Countdown(1)),
+                (Coord(4, 2), GameState::Countdown(0)),
+                (Coord(4, 3), GameState::Playing),
+            ]
+        );
+
+        // A key skips it.
+        let mut recorder = Recorder::default();
+        let mut ctrl = new_controler();
+        ctrl.args.countdown = 3;
+        ctrl.restart();
+        let script = vec![Select, Tick];
+        ctrl.run(&mut recorder, script.into_iter()).unwrap();
+        assert_eq!(recorder.0[1], (Coord(4, 2), GameState::Playing));
+        assert_eq!(recorder.0[2], (Coord(5, 2), GameState::Playing));
+    }
+
+    #[test]
+    fn autopilot_ignores_player() {
+        use SnakeGameEvent::*;
+
+        // The feed is at (10, 10), below the head.
+        let mut recorder = Recorder::default();
+        let mut ctrl = new_controler();
+        ctrl.args.ai = true;
+        let script = vec![ChangeDir(Direction::Up), Tick, Tick];
+        ctrl.run(&mut recorder, script.into_iter()).unwrap();
+
+        assert_eq!(recorder.0[2], (Coord(4, 4), GameState::Playing));
+    }
+
+    #[test]
+    fn other_player_wins_when_snake_crashes() {
+        use SnakeGameEvent::*;
+
+        let mut recorder = Recorder::default();
+        let mut ctrl = new_controler();
+        ctrl.args.two_player = true;
+        ctrl.restart();
+        let script = vec![
+            ChangeDir(Direction::Up),
+            ChangeDir2(Direction::Up),
+            Tick,
+            Tick,
+        ];
+        ctrl.run(&mut recorder, script.into_iter()).unwrap();
+
+        // The first snake hits the wall, while the second one still has room.
+        assert_eq!(
+            recorder.0.last(),
+            Some(&(Coord(4, 1), GameState::Winner(Some(1))))
+        );
+    }
+
+    #[test]
+    fn tall_level_fits_long_snake() {
+        let level = "######\n#  ^ #\n#    #\n#    #\n#    #\n#    #\n#    #\n######";
+        let path = env::temp_dir().join(format!("snake-tall-{}.txt", process::id()));
+        fs::write(&path, level).unwrap();
+        let args = |len: &str| {
+            let path = path.display().to_string();
+            ["--level", &path, "--length", len].map(String::from)
+        };
+        // The snake is longer than the width allows, but fits below the head.
+        let parsed = Args::parse(args("6"), &Config::default());
+        let too_long = Args::parse(args("7"), &Config::default());
+        fs::remove_file(&path).unwrap();
+        let logic = SnakeGameControler::new_logic(&parsed.unwrap(), 0, 0);
+        assert_eq!(logic.body().count(), 6);
+        assert!(too_long.is_err());
+    }
+
+    #[test]
+    fn level_changes_at_score() {
+        use SnakeGameEvent::*;
+
+        let first = "######\n#>@  #\n#    #\n#    #\n#    #\n######";
+        let second = "######\n#    #\n#    #\n#    #\n#  ^ #\n######";
+        let path = env::temp_dir().join(format!("snake-level-{}.toml", process::id()));
+        let mut recorder = Recorder::default();
+        let mut ctrl = new_controler();
+        ctrl.args.levels = vec![Level::parse(first).unwrap(), Level::parse(second).unwrap()];
+        ctrl.args.level_score = 1;
+        ctrl.args.length = 1;
+        ctrl.args.record = Some(path.clone());
+        ctrl.restart();
+        ctrl.run(&mut recorder, vec![Tick].into_iter()).unwrap();
+
+        // Eating the feed goes to the second level, whose snake starts at the bottom.
+        assert_eq!(recorder.0.last(), Some(&(Coord(3, 4), GameState::Playing)));
+        let replay = Replay::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(replay.levels.len(), 2);
+        assert_eq!(replay.score, 1);
+    }
+
+    #[test]
+    fn step_once_while_paused() {
+        use SnakeGameEvent::*;
+
+        let mut recorder = Recorder::default();
+        let mut ctrl = new_controler();
+        ctrl.speed = MAX_SPEED;
+        let script = vec![StepOnce, TogglePause, StepOnce, StepOnce, Slower];
+        ctrl.run(&mut recorder, script.into_iter()).unwrap();
+
+        assert_eq!(
+            recorder.0,
+            vec![
+                (Coord(4, 2), GameState::Playing),
+                (Coord(4, 2), GameState::Paused),
+                (Coord(5, 2), GameState::Paused),
+                (Coord(6, 2), GameState::Paused),
+                (Coord(6, 2), GameState::Paused),
+            ]
+        );
+    }
+
+    #[test]
+    fn undo_takes_back_crash() {
+        use SnakeGameEvent::*;
+
+        let mut recorder = Recorder::default();
+        let mut ctrl = new_controler();
+        ctrl.args.practice = true;
+        ctrl.restart();
+        let script = vec![ChangeDir(Direction::Up), Tick, Tick, Undo, Undo];
+        ctrl.run(&mut recorder, script.into_iter()).unwrap();
+
+        assert_eq!(
+            recorder.0[2..],
+            [
+                (Coord(4, 1), GameState::Over(MoveResult::HitWall)),
+                (Coord(4, 1), GameState::Paused),
+                (Coord(4, 2), GameState::Paused),
+            ]
+        );
+    }
+
+    #[test]
+    fn undo_takes_back_feed_eaten() {
+        let mut recorder = Recorder::default();
+        let mut ctrl = new_controler();
+        ctrl.args.practice = true;
+        ctrl.args.feed_at = Some(Coord(5, 2));
+        ctrl.restart();
+        ctrl.step(&mut recorder).unwrap();
+        ctrl.step(&mut recorder).unwrap();
+        assert_eq!(ctrl.eaten, 1);
+
+        assert!(ctrl.undo());
+        assert_eq!(ctrl.eaten, 1);
+        assert!(ctrl.undo());
+        assert_eq!(ctrl.eaten, 0);
+    }
+
+    #[test]
+    fn frames_are_drawn_between_moves() {
+        /// Wait for the time of a frame, and run out after a few of them.
+        struct Waits(usize);
+
+        impl InputSource for Waits {
+            fn poll_event(&mut self, timeout: Option<Duration>) -> Input {
+                if self.0 == 0 {
+                    return Input::End;
+                }
+                self.0 -= 1;
+                assert!(timeout.is_some_and(|t| t <= FRAME_INTERVAL));
+                std::thread::sleep(timeout.unwrap());
+                Input::Timeout
+            }
+        }
+
+        let mut recorder = Recorder::default();
+        let ctrl = new_controler();
+        let head = *ctrl.logic.body().next().unwrap();
+        ctrl.run(&mut recorder, Waits(3)).unwrap();
+        // The moves are an hour apart.
+        assert_eq!(recorder.0, [(head, GameState::Playing); 4]);
+    }
+
+    #[test]
+    fn acceleration_counts_feeds_eaten() {
+        let mut ctrl = new_controler();
+        ctrl.args.tick = Duration::from_millis(150);
+        ctrl.args.accelerate = true;
+        // A golden feed is one feed, however many points it is worth.
+        ctrl.logic.set_score(5);
+        ctrl.eaten = 1;
+        assert_eq!(ctrl.tick_interval(), Duration::from_millis(145));
+        ctrl.eaten = 100;
+        assert_eq!(ctrl.tick_interval(), Duration::from_millis(60));
+    }
+
+    #[test]
+    fn outcome_of_last_game() {
+        use SnakeGameEvent::*;
+
+        let mut recorder = Recorder::default();
+        let script = [vec![ChangeDir(Direction::Up)], vec![Tick; 20]].concat();
+        let outcome = new_controler().run(&mut recorder, script.into_iter());
+        assert_eq!(outcome.unwrap(), Outcome::GameOver);
+
+        // Quitting in the middle of the next game does not count the one before.
+        let script = [
+            vec![ChangeDir(Direction::Up)],
+            vec![Tick; 20],
+            vec![Restart, Quit, Yes],
+        ]
+        .concat();
+        let outcome = new_controler().run(&mut recorder, script.into_iter());
+        assert_eq!(outcome.unwrap(), Outcome::Quit);
+
+        assert_eq!(Outcome::of(GameState::Winner(Some(1))), Outcome::Won);
+        assert_eq!(Outcome::of(GameState::Over(MoveResult::Won)).code(), 4);
+    }
+
+    #[test]
+    fn trail_is_left_tail() {
+        let mut recorder = Recorder::default();
+        let mut ctrl = new_controler();
+        ctrl.args.trail = true;
+        let tail = *ctrl.logic.body().last().unwrap();
+        ctrl.step(&mut recorder).unwrap();
+        assert_eq!(ctrl.scene().trail, [(tail, 0)]);
+
+        // The cell stays for a few moves, getting older.
+        let next = *ctrl.logic.body().last().unwrap();
+        ctrl.step(&mut recorder).unwrap();
+        ctrl.step(&mut recorder).unwrap();
+        assert_eq!(ctrl.scene().trail[0], (tail, 2));
+        assert!(ctrl.scene().trail.contains(&(next, 1)));
+        ctrl.step(&mut recorder).unwrap();
+        assert!(ctrl.scene().trail.iter().all(|(p, _)| *p != tail));
+        assert_eq!(ctrl.scene().trail.len(), 3);
+
+        ctrl.restart();
+        assert!(ctrl.scene().trail.is_empty());
+    }
+
+    #[test]
+    fn resume_saved_game() {
+        use SnakeGameEvent::*;
+
+        let mut ctrl = new_controler();
+        ctrl.logic.step();
+        let saved = SavedGame::new(&ctrl.logic, Duration::from_secs(5), 0, 0);
+        let saved = serde_json::from_str(&serde_json::to_string(&saved).unwrap()).unwrap();
+
+        let mut recorder = Recorder::default();
+        let mut ctrl = new_controler();
+        ctrl.resume(saved);
+        assert!(ctrl.time.elapsed() >= Duration::from_secs(5));
+        let script = vec![TogglePause, Tick];
+        ctrl.run(&mut recorder, script.into_iter()).unwrap();
+
+        assert_eq!(
+            recorder.0,
+            vec![
+                (Coord(5, 2), GameState::Paused),
+                (Coord(5, 2), GameState::Playing),
+                (Coord(6, 2), GameState::Playing),
+            ]
+        );
+    }
+
+    #[test]
+    fn controls_panel_pauses_game() {
+        use SnakeGameEvent::*;
+
+        let mut recorder = Recorder::default();
+        let script = vec![ShowControls, Tick, Quit, Tick];
+        new_controler()
+            .run(&mut recorder, script.into_iter())
+            .unwrap();
+
+        // The key which closes the panel does nothing else.
+        assert_eq!(
+            recorder.0,
+            vec![
+                (Coord(4, 2), GameState::Playing),
+                (Coord(4, 2), GameState::Paused),
+                (Coord(4, 2), GameState::Playing),
+                (Coord(5, 2), GameState::Playing),
+            ]
+        );
+    }
+
+    #[test]
+    fn name_goes_on_leaderboard() {
+        use SnakeGameEvent::*;
+
+        /// Record the name being typed and the leaderboard of every scene.
+        #[derive(Default)]
+        struct Board(Vec<(Option<String>, Vec<leaderboard::Entry>)>);
+
+        impl Renderer for Board {
+            fn draw(&mut self, scene: &Scene) -> io::Result<()> {
+                let entries = scene.leaderboard.unwrap_or_default().to_vec();
+                self.0.push((scene.name.map(String::from), entries));
+                Ok(())
+            }
+        }
+
+        let mut ctrl = new_controler();
+        ctrl.logic.set_score(3);
+        let script = vec![
+            ChangeDir(Direction::Up),
+            Tick,
+            Tick,
+            Typed('a'),
+            Typed('q'),
+            Erase,
+            Typed('b'),
+            Select,
+        ];
+        let mut board = Board::default();
+        ctrl.run(&mut board, script.into_iter()).unwrap();
+
+        let (name, entries) = &board.0[board.0.len() - 2];
+        assert_eq!(name.as_deref(), Some("ab"));
+        assert!(entries.is_empty());
+        let (name, entries) = board.0.last().unwrap();
+        assert_eq!(*name, None);
+        assert_eq!(
+            *entries,
+            [leaderboard::Entry {
+                name: "ab".to_string(),
+                score: 3
+            }]
+        );
+    }
+
+    #[test]
+    fn quit_asks_first() {
+        use SnakeGameEvent::*;
+
+        let mut recorder = Recorder::default();
+        let script = vec![Quit, No, Tick, Quit, Yes, Tick];
+        new_controler()
+            .run(&mut recorder, script.into_iter())
+            .unwrap();
+
+        assert_eq!(
+            recorder.0,
+            vec![
+                (Coord(4, 2), GameState::Playing),
+                (Coord(4, 2), GameState::Paused),
+                (Coord(4, 2), GameState::Playing),
+                (Coord(5, 2), GameState::Playing),
+                (Coord(5, 2), GameState::Paused),
+            ]
+        );
+    }
+
+    #[test]
+    fn scripted_game() {
+        use SnakeGameEvent::*;
+
+        let mut recorder = Recorder::default();
+        let script = vec![
+            Tick,
+            ChangeDir(Direction::Up),
+            Tick,
+            Tick,
+            Restart,
+            TogglePause,
+            Tick,
+            TogglePause,
+            Tick,
+        ];
+        new_controler()
+            .run(&mut recorder, script.into_iter())
+            .unwrap();
+
+        assert_eq!(
+            recorder.0,
+            vec![
+                (Coord(4, 2), GameState::Playing),
+                (Coord(5, 2), GameState::Playing),
+                (Coord(5, 1), GameState::Playing),
+                (Coord(5, 1), GameState::Over(MoveResult::HitWall)),
+                (Coord(4, 2), GameState::Playing),
+                (Coord(4, 2), GameState::Paused),
+                (Coord(4, 2), GameState::Playing),
+                (Coord(5, 2), GameState::Playing),
+            ]
+        );
+    }
 }
@@ -0,0 +1,129 @@
+//! The menu shown before the game starts.
+
+use super::input::{Input, InputSource};
+use super::render::Renderer;
+use super::{Difficulty, SnakeGameEvent};
+use snake_game::Direction;
+use std::io;
+
+/// Items of the menu, from the top.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Item {
+    Start,
+    Difficulty,
+    Quit,
+}
+
+const ITEMS: [Item; 3] = [Item::Start, Item::Difficulty, Item::Quit];
+
+/// The menu and what is chosen on it.
+pub struct Menu {
+    /// Index of the item under the cursor.
+    selected: usize,
+    pub difficulty: Difficulty,
+}
+
+impl Menu {
+    pub fn new(difficulty: Difficulty) -> Self {
+        Self {
+            selected: 0,
+            difficulty,
+        }
+    }
+
+    /// Lines to draw, with a cursor at the selected item.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec!["SNAKE".to_string(), String::new()];
+        for (i, item) in ITEMS.iter().enumerate() {
+            let cursor = if i == self.selected { '>' } else { ' ' };
+            let label = match item {
+                Item::Start => "Start".to_string(),
+                Item::Difficulty => format!("Difficulty: {}", self.difficulty),
+                Item::Quit => "Quit".to_string(),
+            };
+            lines.push(format!("{} {}", cursor, label));
+        }
+        lines.push(String::new());
+        lines.push("up/down: choose  enter: select".to_string());
+        lines
+    }
+
+    /// Show the menu until the player starts the game or quits.
+    /// Return false if the player quits.
+    pub fn run(
+        &mut self,
+        renderer: &mut impl Renderer,
+        input: &mut impl InputSource,
+    ) -> io::Result<bool> {
+        use SnakeGameEvent::*;
+
+        renderer.draw_menu(self)?;
+        loop {
+            let e = match input.poll_event(None) {
+                Input::Event(e) => e,
+                Input::Timeout => continue,
+                Input::End => return Ok(false),
+            };
+            let item = ITEMS[self.selected];
+            match e {
+                ChangeDir(d) | ChangeDir2(d) => match d {
+                    Direction::Up => self.selected = self.selected.saturating_sub(1),
+                    Direction::Down => self.selected = (self.selected + 1).min(ITEMS.len() - 1),
+                    // Left and right change the difficulty as well.
+                    Direction::Left | Direction::Right if item == Item::Difficulty => {
+                        self.difficulty = self.difficulty.next();
+                    }
+                    _ => continue,
+                },
+                Select => match item {
+                    Item::Start => return Ok(true),
+                    Item::Difficulty => self.difficulty = self.difficulty.next(),
+                    Item::Quit => return Ok(false),
+                },
+                Quit => return Ok(false),
+                Resize(..) => {}
+                _ => continue,
+            }
+            renderer.draw_menu(self)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::render::Scene;
+    use super::*;
+
+    struct NoRenderer;
+
+    impl Renderer for NoRenderer {
+        fn draw(&mut self, _: &Scene) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn choose_difficulty_and_start() {
+        use SnakeGameEvent::*;
+
+        let mut menu = Menu::new(Difficulty::Normal);
+        let script = [
+            ChangeDir(Direction::Down),
+            Select,
+            Select,
+            ChangeDir(Direction::Up),
+            Select,
+        ];
+        assert!(menu.run(&mut NoRenderer, &mut script.into_iter()).unwrap());
+        assert_eq!(menu.difficulty, Difficulty::Easy);
+
+        let mut menu = Menu::new(Difficulty::Normal);
+        let script = [
+            ChangeDir(Direction::Down),
+            ChangeDir(Direction::Down),
+            Select,
+        ];
+        assert!(!menu.run(&mut NoRenderer, &mut script.into_iter()).unwrap());
+        assert_eq!(menu.lines()[4], "> Quit");
+    }
+}
@@ -0,0 +1,1272 @@
+//! Drawing the game.
+
+use super::leaderboard::{Entry, MAX_NAME_LEN};
+use super::menu::Menu;
+use super::{Difficulty, GameState};
+use crossterm::style::{Color, Print, ResetColor, SetForegroundColor};
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, execute, queue};
+use snake_game::{Coord, Direction, MoveResult, Size, SnakeGameLogic};
+use std::fmt::{self, Write as _};
+use std::fs::{File, OpenOptions};
+use std::io::{self, stdout, Stdout, Write};
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Everything to be drawn.
+#[derive(Clone)]
+pub struct Scene<'a> {
+    pub logic: &'a SnakeGameLogic,
+    pub state: GameState,
+    pub high_score: usize,
+    /// Time spent playing.
+    pub elapsed: Duration,
+    /// Remaining time of the time attack.
+    pub time_left: Option<Duration>,
+    /// Number of the level from 1, when playing levels.
+    pub level: Option<usize>,
+    /// Speed of the game in percent of the normal one.
+    pub speed: u32,
+    /// The preset chosen by the player, if any.
+    pub difficulty: Option<Difficulty>,
+    /// Date of the daily challenge, when playing it.
+    pub daily: Option<&'a str>,
+    /// The controls shown below the field, if they are shown.
+    pub help: Option<&'a str>,
+    /// The keys to answer the messages over the field.
+    pub prompts: &'a Prompts,
+    /// Ask the player whether to quit.
+    pub quitting: bool,
+    /// The best scores, shown when the game is over.
+    pub leaderboard: Option<&'a [Entry]>,
+    /// The name the player is typing for the leaderboard.
+    pub name: Option<&'a str>,
+    /// Lines of the panel shown over the field, if it is shown.
+    pub panel: Option<Vec<String>>,
+    /// Draw the snakes in red.
+    pub flash: bool,
+    /// Shift the hue of the bodies along the snakes.
+    pub gradient: bool,
+    /// Head of the snake of the recorded game played along, if any.
+    pub ghost: Option<Coord>,
+    /// Cells the tails left in the last few moves with the number of moves since, drawn fainter as they get older.
+    pub trail: &'a [(Coord, usize)],
+}
+
+impl Scene<'_> {
+    /// Size of the whole frame: the header, the field and the help bar.
+    pub fn frame_size(&self) -> Size {
+        frame_size(drawn_size(self.logic), self.help)
+    }
+}
+
+/// Lines telling the keys to answer the messages drawn over the field.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Prompts {
+    /// Below the name typed for the leaderboard.
+    pub name: String,
+    /// Below the end of the game.
+    pub over: String,
+    /// Asking whether to quit.
+    pub quit: String,
+}
+
+/// Size of the field drawn: the whole field, or the window around the head if it has no walls.
+fn drawn_size(logic: &SnakeGameLogic) -> Size {
+    logic.view().map_or(logic.field_size(), |(_, size)| size)
+}
+
+/// Size of the frame of a field: the header, the field and the help bar if it is shown.
+/// It is as wide as the help bar if the field is narrower.
+/// The header is cut at this width, so that the frame stays in place while the header changes.
+pub fn frame_size(field_size: Size, help: Option<&str>) -> Size {
+    let Size(w, h) = field_size;
+    let help_width = help.map_or(0, |line| line.chars().count() as u16);
+    Size(w.max(help_width), h + 1 + u16::from(help.is_some()))
+}
+
+/// Output backend of the game.
+pub trait Renderer {
+    /// Draw the scene. Called every time the scene changes.
+    fn draw(&mut self, scene: &Scene) -> io::Result<()>;
+
+    /// Make a sound.
+    fn bell(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Show that a snake crashed in the scene, before the game over screen.
+    fn flash(&mut self, _scene: &Scene) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Draw the menu before the game.
+    fn draw_menu(&mut self, _menu: &Menu) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Characters to draw the board with.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub top_left: char,
+    pub top_right: char,
+    pub bottom_left: char,
+    pub bottom_right: char,
+    pub horizontal: char,
+    pub vertical: char,
+    pub empty: char,
+    pub head_up: char,
+    pub head_down: char,
+    pub head_left: char,
+    pub head_right: char,
+    pub body: char,
+    /// Body of the second player's snake.
+    pub body2: char,
+    pub feed: char,
+    pub poison: char,
+    pub golden: char,
+    pub reverse: char,
+    pub star: char,
+    pub slow: char,
+    pub obstacle: char,
+    pub bomb: char,
+    pub portal: char,
+    /// Head of the snake of a recorded game.
+    pub ghost: char,
+    /// Cell a tail has just left.
+    pub trail: char,
+}
+
+impl Default for Theme {
+    /// Plain ASCII, which any terminal can show.
+    fn default() -> Self {
+        Self {
+            top_left: '+',
+            top_right: '+',
+            bottom_left: '+',
+            bottom_right: '+',
+            horizontal: '-',
+            vertical: '|',
+            empty: ' ',
+            head_up: '^',
+            head_down: 'v',
+            head_left: '<',
+            head_right: '>',
+            body: 'x',
+            body2: 'o',
+            feed: '@',
+            poison: '*',
+            golden: '$',
+            reverse: '~',
+            star: '&',
+            slow: '=',
+            obstacle: '#',
+            bomb: 'B',
+            portal: 'O',
+            ghost: '%',
+            trail: '.',
+        }
+    }
+}
+
+impl Theme {
+    /// Walls of box-drawing characters, whose corners connect.
+    /// They are single-width, so the field keeps its size.
+    pub fn box_drawing() -> Self {
+        Self {
+            top_left: '┌',
+            top_right: '┐',
+            bottom_left: '└',
+            bottom_right: '┘',
+            horizontal: '─',
+            vertical: '│',
+            ..Self::default()
+        }
+    }
+}
+
+/// A character on the screen and its color.
+type Tile = (char, Option<Color>);
+
+/// Stdout in the raw mode.
+/// The terminal is restored when this is dropped, even if the game panics.
+struct Terminal {
+    stdout: Stdout,
+}
+
+impl Terminal {
+    fn new() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        // Restore the terminal by dropping this even if the rest fails.
+        let mut terminal = Self { stdout: stdout() };
+        execute!(terminal, terminal::Clear(ClearType::All), cursor::Hide)?;
+        Ok(terminal)
+    }
+}
+
+impl Deref for Terminal {
+    type Target = Stdout;
+
+    fn deref(&self) -> &Stdout {
+        &self.stdout
+    }
+}
+
+impl DerefMut for Terminal {
+    fn deref_mut(&mut self) -> &mut Stdout {
+        &mut self.stdout
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        // Keep the screen on panic so that the message is not erased.
+        if !thread::panicking() {
+            let _ = queue!(
+                self.stdout,
+                terminal::Clear(ClearType::All),
+                cursor::MoveTo(0, 0)
+            );
+        }
+        restore_terminal();
+    }
+}
+
+/// Show the cursor and leave the raw mode.
+/// It is also for the signal handler, which cannot reach the renderer.
+pub fn restore_terminal() {
+    let _ = execute!(stdout(), cursor::Show);
+    let _ = terminal::disable_raw_mode();
+}
+
+/// Draw the game on the terminal.
+pub struct TermRenderer {
+    stdout: Terminal,
+    /// Draw the board with colors.
+    color: bool,
+    theme: Theme,
+    /// Top-left corner and size of the field of the last frame.
+    frame: Option<((u16, u16), Size)>,
+    /// Buffer to build the screen in.
+    screen: Vec<Vec<Tile>>,
+    /// Screen drawn last.
+    prev: Vec<Vec<Tile>>,
+    /// Draw the next frame from scratch instead of the difference from `prev`.
+    redraw: bool,
+}
+
+impl TermRenderer {
+    /// Put the terminal in the raw mode.
+    /// It is restored when the renderer is dropped.
+    pub fn new(color: bool, theme: Theme) -> io::Result<Self> {
+        Ok(Self {
+            stdout: Terminal::new()?,
+            color,
+            theme,
+            frame: None,
+            screen: Vec::new(),
+            prev: Vec::new(),
+            redraw: true,
+        })
+    }
+
+    /// Top-left corner to draw the frame at, so that the field is at the center of the terminal.
+    /// Return None if the terminal is too small for the frame.
+    fn origin(scene: &Scene) -> Option<(u16, u16)> {
+        let Size(w, h) = scene.frame_size();
+        match terminal::size() {
+            Ok((cols, rows)) if cols < w || rows < h => None,
+            Ok((cols, rows)) => Some(((cols - w) / 2, (rows - h) / 2)),
+            Err(_) => Some((0, 0)),
+        }
+    }
+
+    /// Tell the player to enlarge the terminal, instead of drawing a broken frame.
+    fn render_too_small(&mut self, scene: &Scene) -> io::Result<()> {
+        let Size(w, h) = scene.frame_size();
+        queue!(
+            self.stdout,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+        write!(
+            self.stdout,
+            "The terminal is too small. {}x{} is required.",
+            w, h
+        )
+    }
+
+    /// Draw the final score over the field.
+    fn render_game_over(&mut self, scene: &Scene, origin: (u16, u16)) -> io::Result<()> {
+        let (title, reason) = match scene.state {
+            GameState::Over(MoveResult::Won) => ("YOU WIN", "filled the field".to_string()),
+            GameState::Over(MoveResult::HitWall) => {
+                ("GAME OVER", "crashed into the wall".to_string())
+            }
+            GameState::Over(MoveResult::HitObstacle) => {
+                ("GAME OVER", "crashed into an obstacle".to_string())
+            }
+            GameState::Over(MoveResult::HitBomb) => ("GAME OVER", "blew up".to_string()),
+            GameState::Over(MoveResult::HitSelf) => ("GAME OVER", "ate yourself".to_string()),
+            GameState::Over(MoveResult::Poisoned) => ("GAME OVER", "poisoned".to_string()),
+            GameState::TimeUp => ("TIME UP", "the time attack is over".to_string()),
+            GameState::ReplayEnd(score) if score == scene.logic.score() => {
+                ("REPLAY END", "the score matches the recording".to_string())
+            }
+            GameState::ReplayEnd(score) => (
+                "REPLAY END",
+                format!("the recording scored {} instead", score),
+            ),
+            GameState::Winner(Some(player)) => {
+                let title = if player == 0 {
+                    "PLAYER 1 WINS"
+                } else {
+                    "PLAYER 2 WINS"
+                };
+                (title, scores(scene.logic))
+            }
+            GameState::Winner(None) => ("DRAW", scores(scene.logic)),
+            _ => return Ok(()),
+        };
+        let title = match scene.state {
+            GameState::Winner(_) => format!(" {} ", title),
+            _ => format!(" {} — score: {} ", title, scene.logic.score()),
+        };
+        let mut lines = vec![title, format!(" {} ", reason)];
+        if let Some(name) = scene.name {
+            lines.push(" NEW RECORD! Enter your name ".to_string());
+            lines.push(format!(
+                " > {:<width$} ",
+                format!("{}_", name),
+                width = MAX_NAME_LEN + 1
+            ));
+            lines.push(scene.prompts.name.clone());
+        } else {
+            if let Some(entries) = scene.leaderboard.filter(|e| !e.is_empty()) {
+                lines.push(String::new());
+                for (i, entry) in entries.iter().enumerate() {
+                    lines.push(format!(
+                        " {:>2}. {:<width$} {:>5} ",
+                        i + 1,
+                        entry.name,
+                        entry.score,
+                        width = MAX_NAME_LEN
+                    ));
+                }
+                lines.push(String::new());
+            }
+            lines.push(scene.prompts.over.clone());
+        }
+        self.render_message(scene, origin, &lines)
+    }
+
+    /// Draw the panel of the controls over the field.
+    /// The lines are padded to the same width so that the panel hides the field behind it.
+    fn render_help(
+        &mut self,
+        scene: &Scene,
+        origin: (u16, u16),
+        lines: &[String],
+    ) -> io::Result<()> {
+        let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        let lines: Vec<String> = lines
+            .iter()
+            .map(|line| format!(" {:<width$} ", line))
+            .collect();
+        self.render_message(scene, origin, &lines)
+    }
+
+    /// Draw the lines at the center of the field.
+    fn render_message(
+        &mut self,
+        scene: &Scene,
+        (x, y): (u16, u16),
+        lines: &[impl AsRef<str>],
+    ) -> io::Result<()> {
+        let Size(w, h) = drawn_size(scene.logic);
+        // The field is drawn from the 2nd row.
+        let top = y + 1 + (h / 2).saturating_sub(lines.len() as u16 / 2);
+        for (i, line) in lines.iter().enumerate() {
+            let line = line.as_ref();
+            let len = line.chars().count() as u16;
+            let left = x + w.saturating_sub(len) / 2;
+            queue!(self.stdout, cursor::MoveTo(left, top + i as u16))?;
+            write!(self.stdout, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl Renderer for TermRenderer {
+    fn bell(&mut self) -> io::Result<()> {
+        // The bell does not move the cursor, so it does not break the frame.
+        write!(self.stdout, "\x07")?;
+        self.stdout.flush()
+    }
+
+    /// Blink the snakes in red a few times, for a few hundred milliseconds in all.
+    fn flash(&mut self, scene: &Scene) -> io::Result<()> {
+        const TIMES: usize = 2;
+        const INTERVAL: Duration = Duration::from_millis(80);
+
+        let red = Scene {
+            flash: true,
+            ..scene.clone()
+        };
+        for _ in 0..TIMES {
+            self.draw(&red)?;
+            thread::sleep(INTERVAL);
+            self.draw(scene)?;
+            thread::sleep(INTERVAL);
+        }
+        Ok(())
+    }
+
+    fn draw_menu(&mut self, menu: &Menu) -> io::Result<()> {
+        // The game is drawn on a clear screen after this.
+        self.frame = None;
+        let lines = menu.lines();
+        let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0) as u16;
+        let (cols, rows) = terminal::size().unwrap_or((width, lines.len() as u16));
+        let left = cols.saturating_sub(width) / 2;
+        let top = rows.saturating_sub(lines.len() as u16) / 2;
+        queue!(self.stdout, terminal::Clear(ClearType::All))?;
+        for (i, line) in lines.iter().enumerate() {
+            queue!(self.stdout, cursor::MoveTo(left, top + i as u16))?;
+            write!(self.stdout, "{}", line)?;
+        }
+        self.stdout.flush()
+    }
+
+    fn draw(&mut self, scene: &Scene) -> io::Result<()> {
+        let Some(origin) = Self::origin(scene) else {
+            self.frame = None;
+            self.render_too_small(scene)?;
+            return self.stdout.flush();
+        };
+        let frame = (origin, drawn_size(scene.logic));
+        if self.frame != Some(frame) {
+            // Erase the frame drawn at the old place or in the old size.
+            queue!(self.stdout, terminal::Clear(ClearType::All))?;
+            self.frame = Some(frame);
+            self.redraw = true;
+        }
+
+        let (x, y) = origin;
+        fill_screen(scene, &self.theme, &mut self.screen);
+        clip_header(scene, &mut self.screen);
+        if self.redraw {
+            for (i, tiles) in self.screen.iter().enumerate() {
+                queue!(self.stdout, cursor::MoveTo(x, y + i as u16))?;
+                for &tile in tiles {
+                    write_tile(&mut *self.stdout, tile, self.color)?;
+                }
+                queue!(self.stdout, terminal::Clear(ClearType::UntilNewLine))?;
+            }
+        } else {
+            // Only rewrite the cells changed since the last frame.
+            for (i, j) in diff(&self.prev, &self.screen) {
+                queue!(self.stdout, cursor::MoveTo(x + j as u16, y + i as u16))?;
+                write_tile(&mut *self.stdout, self.screen[i][j], self.color)?;
+            }
+            for (i, (prev, tiles)) in self.prev.iter().zip(&self.screen).enumerate() {
+                if tiles.len() < prev.len() {
+                    queue!(
+                        self.stdout,
+                        cursor::MoveTo(x + tiles.len() as u16, y + i as u16),
+                        terminal::Clear(ClearType::UntilNewLine)
+                    )?;
+                }
+            }
+        }
+        self.render_game_over(scene, origin)?;
+        if let GameState::Countdown(secs) = scene.state {
+            let text = match secs {
+                0 => " Go! ".to_string(),
+                secs => format!(" {}... ", secs),
+            };
+            self.render_message(scene, origin, &[text])?;
+        }
+        if scene.quitting {
+            self.render_message(scene, origin, &[&scene.prompts.quit])?;
+        }
+        if let Some(lines) = &scene.panel {
+            self.render_help(scene, origin, lines)?;
+        }
+        self.stdout.flush()?;
+
+        // The messages are not a part of the screen.
+        self.redraw = scene.state.is_over()
+            || matches!(scene.state, GameState::Countdown(_))
+            || scene.quitting
+            || scene.panel.is_some();
+        mem::swap(&mut self.prev, &mut self.screen);
+        Ok(())
+    }
+}
+
+/// Append every frame to a log file as plain text, and draw it with another renderer as well.
+pub struct FileRenderer<R, W = File> {
+    inner: R,
+    log: W,
+    theme: Theme,
+    /// Buffer to build the screen in.
+    screen: Vec<Vec<Tile>>,
+}
+
+impl<R: Renderer> FileRenderer<R> {
+    /// Append to the file, which is created if missing.
+    pub fn new(path: &Path, inner: R, theme: Theme) -> io::Result<Self> {
+        let log = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            inner,
+            log,
+            theme,
+            screen: Vec::new(),
+        })
+    }
+}
+
+impl<R: Renderer, W: Write> Renderer for FileRenderer<R, W> {
+    fn bell(&mut self) -> io::Result<()> {
+        self.inner.bell()
+    }
+
+    fn flash(&mut self, scene: &Scene) -> io::Result<()> {
+        self.inner.flash(scene)
+    }
+
+    fn draw_menu(&mut self, menu: &Menu) -> io::Result<()> {
+        self.inner.draw_menu(menu)
+    }
+
+    /// Each frame starts with a line of the time in seconds since the Unix epoch.
+    fn draw(&mut self, scene: &Scene) -> io::Result<()> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        writeln!(self.log, "--- {}.{:03}", now.as_secs(), now.subsec_millis())?;
+        fill_screen(scene, &self.theme, &mut self.screen);
+        for tiles in &self.screen {
+            let line: String = tiles.iter().map(|t| t.0).collect();
+            writeln!(self.log, "{}", line.trim_end())?;
+        }
+        // Keep the frames written so far if the game crashes.
+        self.log.flush()?;
+        self.inner.draw(scene)
+    }
+}
+
+/// Write every frame to an asciicast v2 file, which `asciinema play` shows, and draw it with another renderer as well.
+///
+/// The first line is the header, `{"version": 2, "width": W, "height": H, "timestamp": T}`,
+/// with the size of the frame and the time it started in seconds since the Unix epoch.
+/// Each following line is a frame, `[S, "o", TEXT]`, where S is the time in seconds since the start
+/// and TEXT clears the terminal and writes the lines of the frame.
+pub struct CastRenderer<R, W = File> {
+    inner: R,
+    out: W,
+    theme: Theme,
+    color: bool,
+    start: Instant,
+    /// Buffer to build the screen in.
+    screen: Vec<Vec<Tile>>,
+}
+
+impl<R: Renderer> CastRenderer<R> {
+    /// Create the file for frames of the size, replacing an old one.
+    pub fn new(path: &Path, size: Size, inner: R, theme: Theme, color: bool) -> io::Result<Self> {
+        Self::with_output(File::create(path)?, size, inner, theme, color)
+    }
+}
+
+impl<R: Renderer, W: Write> CastRenderer<R, W> {
+    fn with_output(
+        mut out: W,
+        Size(width, height): Size,
+        inner: R,
+        theme: Theme,
+        color: bool,
+    ) -> io::Result<Self> {
+        let timestamp = (SystemTime::now().duration_since(UNIX_EPOCH))
+            .unwrap_or_default()
+            .as_secs();
+        let header = serde_json::json!({
+            "version": 2,
+            "width": width,
+            "height": height,
+            "timestamp": timestamp,
+        });
+        writeln!(out, "{}", header)?;
+        Ok(Self {
+            inner,
+            out,
+            theme,
+            color,
+            start: Instant::now(),
+            screen: Vec::new(),
+        })
+    }
+}
+
+impl<R: Renderer, W: Write> Renderer for CastRenderer<R, W> {
+    fn bell(&mut self) -> io::Result<()> {
+        self.inner.bell()
+    }
+
+    fn flash(&mut self, scene: &Scene) -> io::Result<()> {
+        self.inner.flash(scene)
+    }
+
+    fn draw_menu(&mut self, menu: &Menu) -> io::Result<()> {
+        self.inner.draw_menu(menu)
+    }
+
+    fn draw(&mut self, scene: &Scene) -> io::Result<()> {
+        fill_screen(scene, &self.theme, &mut self.screen);
+        clip_header(scene, &mut self.screen);
+        let mut text = Vec::new();
+        queue!(text, terminal::Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+        for (i, tiles) in self.screen.iter().enumerate() {
+            if i > 0 {
+                text.extend(b"\r\n");
+            }
+            for &tile in tiles {
+                write_tile(&mut text, tile, self.color)?;
+            }
+        }
+        let text = String::from_utf8(text).map_err(io::Error::other)?;
+        let time = self.start.elapsed().as_secs_f64();
+        writeln!(self.out, "{}", serde_json::json!([time, "o", text]))?;
+        // Keep the frames written so far if the game crashes.
+        self.out.flush()?;
+        self.inner.draw(scene)
+    }
+}
+
+/// A renderer chosen at run time, such as the terminal wrapped by the ones writing files.
+impl<R: Renderer + ?Sized> Renderer for Box<R> {
+    fn draw(&mut self, scene: &Scene) -> io::Result<()> {
+        (**self).draw(scene)
+    }
+
+    fn bell(&mut self) -> io::Result<()> {
+        (**self).bell()
+    }
+
+    fn flash(&mut self, scene: &Scene) -> io::Result<()> {
+        (**self).flash(scene)
+    }
+
+    fn draw_menu(&mut self, menu: &Menu) -> io::Result<()> {
+        (**self).draw_menu(menu)
+    }
+}
+
+/// Write a tile of the screen, with the escape sequences of its color.
+fn write_tile(out: &mut impl Write, (c, fg): Tile, color: bool) -> io::Result<()> {
+    match fg {
+        Some(fg) if color => queue!(out, SetForegroundColor(fg), Print(c), ResetColor),
+        _ => queue!(out, Print(c)),
+    }
+}
+
+/// Tiles which differ between the screens, as (row, column) of the new one.
+fn diff<'a>(
+    prev: &'a [Vec<Tile>],
+    next: &'a [Vec<Tile>],
+) -> impl Iterator<Item = (usize, usize)> + 'a {
+    next.iter().enumerate().flat_map(move |(i, tiles)| {
+        tiles
+            .iter()
+            .enumerate()
+            .filter(move |&(j, t)| prev.get(i).and_then(|row| row.get(j)) != Some(t))
+            .map(move |(j, _)| (i, j))
+    })
+}
+
+/// Append what is written to a line of the screen, without colors.
+struct Text<'a>(&'a mut Vec<Tile>);
+
+impl fmt::Write for Text<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.extend(s.chars().map(|c| (c, None)));
+        Ok(())
+    }
+}
+
+/// Scores of both players of a two-player game.
+fn scores(logic: &SnakeGameLogic) -> String {
+    format!("P1: {}  P2: {}", logic.score_of(0), logic.score_of(1))
+}
+
+/// Number of colors around the wheel of [`hue`].
+const HUE_STEPS: usize = 30;
+
+/// A color of the wheel of the 256-color palette, from green through cyan, blue, magenta, red and yellow.
+fn hue(step: usize) -> Color {
+    let (edge, k) = (step % HUE_STEPS / 5, (step % 5) as u8);
+    let (r, g, b) = match edge {
+        0 => (0, 5, k),
+        1 => (0, 5 - k, 5),
+        2 => (k, 0, 5),
+        3 => (5, 0, 5 - k),
+        4 => (5, k, 0),
+        _ => (5 - k, 5, 0),
+    };
+    Color::AnsiValue(16 + 36 * r + 6 * g + b)
+}
+
+/// Write the header line: the scores and the state of the game.
+fn write_header(scene: &Scene, header: &mut impl fmt::Write) -> fmt::Result {
+    let logic = scene.logic;
+    if logic.n_snakes() > 1 {
+        write!(
+            header,
+            "P1: {}  P2: {}",
+            logic.score_of(0),
+            logic.score_of(1)
+        )?;
+    } else {
+        write!(
+            header,
+            "score: {}  high score: {}",
+            logic.score(),
+            scene.high_score
+        )?;
+    }
+    if let Some(level) = scene.level {
+        write!(header, "  level: {}", level)?;
+    }
+    if logic.lives() > 1 {
+        write!(header, "  lives: {}", logic.lives_left())?;
+        if logic.n_snakes() > 1 {
+            write!(header, "-{}", logic.lives_left_of(1))?;
+        }
+    }
+    if logic.combo() > 1 || (logic.n_snakes() > 1 && logic.combo_of(1) > 1) {
+        write!(header, "  combo: x{}", logic.combo())?;
+        if logic.n_snakes() > 1 {
+            write!(header, "-x{}", logic.combo_of(1))?;
+        }
+    }
+    if logic.reversed_of(0) > 0 || (logic.n_snakes() > 1 && logic.reversed_of(1) > 0) {
+        write!(header, "  reversed: {}", logic.reversed_of(0))?;
+        if logic.n_snakes() > 1 {
+            write!(header, "-{}", logic.reversed_of(1))?;
+        }
+    }
+    if logic.invincible_of(0) > 0 || (logic.n_snakes() > 1 && logic.invincible_of(1) > 0) {
+        write!(header, "  invincible: {}", logic.invincible_of(0))?;
+        if logic.n_snakes() > 1 {
+            write!(header, "-{}", logic.invincible_of(1))?;
+        }
+    }
+    if logic.slow_left() > 0 {
+        write!(header, "  slow-mo: {}", logic.slow_left())?;
+    }
+    if logic.grace_left() > 0 {
+        write!(header, "  grace: {}", logic.grace_left())?;
+    }
+    let (label, secs) = match scene.time_left {
+        // Round up so that it reaches 00:00 when the time is up.
+        Some(left) => (
+            "time left",
+            left.as_secs() + u64::from(left.subsec_nanos() > 0),
+        ),
+        None => ("time", scene.elapsed.as_secs()),
+    };
+    write!(header, "  {}: {:02}:{:02}", label, secs / 60, secs % 60)?;
+    if let Some((left, ttl)) = logic.feed_lifetime() {
+        // A bar which shrinks until the feeds move.
+        const BAR_LEN: usize = 8;
+        header.write_str("  feed: ")?;
+        for _ in 0..(BAR_LEN * left).div_ceil(ttl) {
+            header.write_char('|')?;
+        }
+    }
+    if scene.speed != 100 {
+        write!(header, "  speed: {}%", scene.speed)?;
+    }
+    if let Some(difficulty) = scene.difficulty {
+        write!(header, "  {}", difficulty)?;
+    }
+    if let Some(date) = scene.daily {
+        write!(header, "  daily: {}", date)?;
+    }
+    match scene.state {
+        GameState::Countdown(_) | GameState::Playing => {}
+        GameState::Paused => header.write_str("  PAUSED")?,
+        GameState::Over(_) | GameState::TimeUp | GameState::ReplayEnd(_) | GameState::Winner(_) => {
+        }
+    }
+    Ok(())
+}
+
+/// Cut the header line of the screen at the width of the frame.
+fn clip_header(scene: &Scene, screen: &mut [Vec<Tile>]) {
+    let Size(w, _) = scene.frame_size();
+    screen[0].truncate(usize::from(w));
+}
+
+/// Fill the tiles of the whole screen: the header line, the field and the help bar.
+/// The buffer is reused so that drawing a frame does not allocate.
+pub(crate) fn fill_screen(scene: &Scene, theme: &Theme, screen: &mut Vec<Vec<Tile>>) {
+    let logic = scene.logic;
+    let view = logic.view();
+    let Coord(left, top) = view.map_or(Coord(0, 0), |(origin, _)| origin);
+    let Size(w, h) = drawn_size(logic);
+    let (w, h) = (w as usize, h as usize);
+
+    screen.resize_with(h + 1 + usize::from(scene.help.is_some()), Vec::new);
+    screen.iter_mut().for_each(Vec::clear);
+
+    // header
+    write_header(scene, &mut Text(&mut screen[0])).unwrap();
+
+    // wall
+    let wall = |c| (c, Some(Color::DarkBlue));
+    let tile_matrix = &mut screen[1..=h];
+    for (y, row) in tile_matrix.iter_mut().enumerate() {
+        if view.is_some() {
+            row.resize(w, (theme.empty, None));
+            continue;
+        }
+        let (left, inner, right) = if y == 0 {
+            (theme.top_left, wall(theme.horizontal), theme.top_right)
+        } else if y == h - 1 {
+            (
+                theme.bottom_left,
+                wall(theme.horizontal),
+                theme.bottom_right,
+            )
+        } else {
+            (theme.vertical, (theme.empty, None), theme.vertical)
+        };
+        row.push(wall(left));
+        row.resize(w - 1, inner);
+        row.push(wall(right));
+    }
+
+    // Put a tile on the cell of the field, unless it is out of the window.
+    let mut put = |&Coord(x, y): &Coord, tile: Tile| {
+        let (Some(x), Some(y)) = (x.checked_sub(left), y.checked_sub(top)) else {
+            return;
+        };
+        if let Some(t) = (tile_matrix.get_mut(y as usize)).and_then(|row| row.get_mut(x as usize)) {
+            *t = tile;
+        }
+    };
+
+    // The trail and the ghost go under everything else, and the ghost off a field of another size.
+    for (p, age) in scene.trail {
+        // From the dark grey of the 256-color palette to almost black.
+        let grey = 244u8.saturating_sub(4 * *age as u8).max(233);
+        put(p, (theme.trail, Some(Color::AnsiValue(grey))));
+    }
+    if let Some(p @ Coord(x, y)) = scene.ghost {
+        let (x, y) = (x as usize, y as usize);
+        if view.is_some() || ((1..w - 1).contains(&x) && (1..h - 1).contains(&y)) {
+            put(&p, (theme.ghost, Some(Color::DarkGrey)));
+        }
+    }
+
+    // obstacles
+    for p in logic.obstacles() {
+        put(p, (theme.obstacle, Some(Color::DarkYellow)));
+    }
+    for p in logic.bombs() {
+        put(p, (theme.bomb, Some(Color::Red)));
+    }
+    for (a, b) in logic.portals() {
+        put(a, (theme.portal, Some(Color::Blue)));
+        put(b, (theme.portal, Some(Color::Blue)));
+    }
+
+    // head & body
+    for player in 0..logic.n_snakes() {
+        let (body_char, head_color, body_color) = if scene.flash {
+            let body_char = if player == 0 { theme.body } else { theme.body2 };
+            (body_char, Color::Red, Color::Red)
+        } else if logic.invincible_of(player) % 2 == 1 {
+            // An invincible snake blinks at each step.
+            let body_char = if player == 0 { theme.body } else { theme.body2 };
+            (body_char, Color::Yellow, Color::Yellow)
+        } else if player == 0 {
+            (theme.body, Color::Green, Color::DarkGreen)
+        } else {
+            (theme.body2, Color::Cyan, Color::DarkCyan)
+        };
+        let mut body = logic.body_of(player);
+        let head_pos = body.next().unwrap();
+        let head_char = match logic.get_dir_of(player) {
+            Direction::Up => theme.head_up,
+            Direction::Down => theme.head_down,
+            Direction::Left => theme.head_left,
+            Direction::Right => theme.head_right,
+        };
+        // The colors of the players start at the opposite sides of the wheel.
+        let shift =
+            (scene.gradient && !scene.flash && logic.invincible_of(player).is_multiple_of(2))
+                .then_some(player * HUE_STEPS / 2);
+        // The head goes over the body, which it can cross while invincible.
+        for (i, p) in body.enumerate() {
+            let color = shift.map_or(body_color, |shift| hue(shift + i));
+            put(p, (body_char, Some(color)));
+        }
+        put(head_pos, (head_char, Some(head_color)));
+    }
+
+    // feed
+    for p in logic.feeds() {
+        put(p, (theme.feed, Some(Color::DarkRed)));
+    }
+    if let Some(p) = logic.poison() {
+        put(&p, (theme.poison, Some(Color::DarkMagenta)));
+    }
+    if let Some(p) = logic.golden() {
+        put(&p, (theme.golden, Some(Color::Yellow)));
+    }
+    if let Some(p) = logic.reverse() {
+        put(&p, (theme.reverse, Some(Color::Magenta)));
+    }
+    if let Some(p) = logic.star() {
+        put(&p, (theme.star, Some(Color::White)));
+    }
+    if let Some(p) = logic.slow() {
+        put(&p, (theme.slow, Some(Color::Blue)));
+    }
+
+    // help
+    if let Some(line) = scene.help {
+        Text(&mut screen[h + 1]).write_str(line).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::input::Keymap;
+    use super::*;
+    use std::sync::OnceLock;
+
+    fn new_logic() -> SnakeGameLogic {
+        SnakeGameLogic::new(Size(20, 20), 1, 0)
+    }
+
+    static PROMPTS: OnceLock<Prompts> = OnceLock::new();
+
+    fn scene(logic: &SnakeGameLogic) -> Scene<'_> {
+        Scene {
+            logic,
+            state: GameState::Playing,
+            high_score: 0,
+            elapsed: Duration::ZERO,
+            time_left: None,
+            level: None,
+            speed: 100,
+            difficulty: None,
+            daily: None,
+            help: None,
+            prompts: PROMPTS.get_or_init(|| Keymap::default().prompts()),
+            quitting: false,
+            leaderboard: None,
+            name: None,
+            panel: None,
+            flash: false,
+            gradient: false,
+            ghost: None,
+            trail: &[],
+        }
+    }
+
+    fn screen(scene: &Scene) -> Vec<Vec<Tile>> {
+        let mut screen = Vec::new();
+        fill_screen(scene, &Theme::default(), &mut screen);
+        screen
+    }
+
+    fn lines(scene: &Scene) -> Vec<String> {
+        screen(scene)
+            .iter()
+            .map(|tiles| tiles.iter().map(|t| t.0).collect())
+            .collect()
+    }
+
+    #[test]
+    fn frame_of_new_game() {
+        let logic = new_logic();
+        let lines = lines(&scene(&logic));
+
+        assert_eq!(lines.len(), 21);
+        assert_eq!(lines[0], "score: 0  high score: 0  time: 00:00");
+        assert_eq!(lines[1], format!("+{}+", "-".repeat(18)));
+        assert_eq!(lines[2], format!("|{}|", " ".repeat(18)));
+        assert_eq!(lines[3], format!("| xx>{}|", " ".repeat(14)));
+        // The feed is at (10, 10).
+        assert_eq!(lines[11], format!("|{}@{}|", " ".repeat(9), " ".repeat(8)));
+        assert_eq!(lines[20], format!("+{}+", "-".repeat(18)));
+    }
+
+    #[test]
+    fn frame_of_wide_field() {
+        let mut logic = SnakeGameLogic::new(Size(40, 15), 1, 0);
+        let frame = lines(&scene(&logic));
+
+        assert_eq!(scene(&logic).frame_size(), Size(40, 16));
+        assert_eq!(frame.len(), 16);
+        assert_eq!(frame[1], format!("+{}+", "-".repeat(38)));
+        assert_eq!(frame[15], format!("+{}+", "-".repeat(38)));
+        assert!(frame[2..15].iter().all(|line| line.chars().count() == 40
+            && line.starts_with('|')
+            && line.ends_with('|')));
+        assert_eq!(frame[3], format!("| xx>{}|", " ".repeat(34)));
+        // The feed is at (20, 7).
+        assert_eq!(frame[8], format!("|{}@{}|", " ".repeat(19), " ".repeat(18)));
+
+        // The head reaches the row above the bottom wall.
+        logic.set_dir(Direction::Down);
+        for _ in 0..11 {
+            logic.step();
+        }
+        let frame = lines(&scene(&logic));
+        assert_eq!(frame[14], format!("|   v{}|", " ".repeat(34)));
+    }
+
+    #[test]
+    fn frame_of_tall_field() {
+        let logic = SnakeGameLogic::new(Size(15, 40), 1, 0);
+        let lines = lines(&scene(&logic));
+
+        assert_eq!(lines.len(), 41);
+        assert_eq!(lines[1], format!("+{}+", "-".repeat(13)));
+        assert_eq!(lines[40], format!("+{}+", "-".repeat(13)));
+        assert_eq!(lines[3], format!("| xx>{}|", " ".repeat(9)));
+        // The feed is at (7, 20).
+        assert_eq!(lines[21], format!("|{}@{}|", " ".repeat(6), " ".repeat(6)));
+    }
+
+    #[test]
+    fn frames_are_cast() {
+        struct NoRenderer;
+
+        impl Renderer for NoRenderer {
+            fn draw(&mut self, _: &Scene) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let logic = new_logic();
+        let size = scene(&logic).frame_size();
+        let mut renderer =
+            CastRenderer::with_output(Vec::new(), size, NoRenderer, Theme::default(), false)
+                .unwrap();
+        renderer.draw(&scene(&logic)).unwrap();
+
+        let cast = String::from_utf8(renderer.out).unwrap();
+        let lines: Vec<serde_json::Value> = cast
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["version"], 2);
+        assert_eq!(lines[0]["width"], 20);
+        assert_eq!(lines[0]["height"], 21);
+        assert!(lines[1][0].as_f64().unwrap() >= 0.0);
+        assert_eq!(lines[1][1], "o");
+        let text = lines[1][2].as_str().unwrap();
+        assert!(text.starts_with("\x1b[2J\x1b[1;1H"));
+        // The header is cut at the width of the field.
+        assert!(text.contains("score: 0  high score\r\n"));
+        assert!(text.contains(&format!("\r\n| xx>{}|\r\n", " ".repeat(14))));
+    }
+
+    #[test]
+    fn frame_around_head_without_walls() {
+        let logic = SnakeGameLogic::without_walls(Size(12, 8), 1, 0, 3);
+        let lines = lines(&scene(&logic));
+
+        assert_eq!(lines.len(), 9);
+        assert_eq!(lines[1], " ".repeat(12));
+        assert_eq!(lines[5], format!("    xx>{}", " ".repeat(5)));
+        assert_eq!(lines[7], format!("{}@{}", " ".repeat(6), " ".repeat(5)));
+        assert_eq!(scene(&logic).frame_size(), Size(12, 9));
+    }
+
+    #[test]
+    fn box_drawing_walls() {
+        let logic = new_logic();
+        let mut screen = Vec::new();
+        fill_screen(&scene(&logic), &Theme::box_drawing(), &mut screen);
+        let lines: Vec<String> = screen
+            .iter()
+            .map(|tiles| tiles.iter().map(|t| t.0).collect())
+            .collect();
+
+        assert_eq!(lines[1], format!("┌{}┐", "─".repeat(18)));
+        assert_eq!(lines[2], format!("│{}│", " ".repeat(18)));
+        assert_eq!(lines[20], format!("└{}┘", "─".repeat(18)));
+        assert!(screen[1..].iter().all(|tiles| tiles.len() == 20));
+    }
+
+    #[test]
+    fn feed_timer_bar_shrinks() {
+        let mut logic = new_logic();
+        logic.set_feed_ttl(Some(4));
+        logic.step();
+        let lines = lines(&scene(&logic));
+
+        assert_eq!(
+            lines[0],
+            "score: 0  high score: 0  time: 00:00  feed: ||||||"
+        );
+    }
+
+    #[test]
+    fn lives_header() {
+        let mut logic = new_logic();
+        logic.set_lives(3);
+        let lines = lines(&scene(&logic));
+
+        assert_eq!(lines[0], "score: 0  high score: 0  lives: 3  time: 00:00");
+    }
+
+    #[test]
+    fn help_bar_below_field() {
+        let logic = new_logic();
+        let mut scene = scene(&logic);
+        let help = "hjkl: move  p: pause  r: restart  q: quit  ?: all keys  tab: hide this";
+        scene.help = Some(help);
+        let lines = lines(&scene);
+
+        // The frame is as wide as the help bar.
+        assert_eq!(scene.frame_size(), Size(70, 22));
+        assert_eq!(lines.len(), 22);
+        assert_eq!(lines[20], format!("+{}+", "-".repeat(18)));
+        assert_eq!(lines[21], help);
+    }
+
+    #[test]
+    fn two_player_frame() {
+        let mut logic = new_logic();
+        logic.add_snake();
+        let scene = scene(&logic);
+        let lines = lines(&scene);
+
+        assert_eq!(lines[0], "P1: 0  P2: 0  time: 00:00");
+        assert_eq!(lines[18], format!("|{}<oo |", " ".repeat(14)));
+        assert_eq!(screen(&scene)[18][15].1, Some(Color::Cyan));
+    }
+
+    #[test]
+    fn body_color_shifts_along_snake() {
+        let logic = new_logic();
+        let mut scene = scene(&logic);
+        scene.gradient = true;
+        let screen = screen(&scene);
+        for (i, &Coord(x, y)) in logic.body().skip(1).enumerate() {
+            // The first row is the header.
+            assert_eq!(screen[y as usize + 1][x as usize].1, Some(hue(i)));
+        }
+        assert_eq!(hue(0), Color::AnsiValue(46));
+        assert_eq!(hue(HUE_STEPS), hue(0));
+    }
+
+    #[test]
+    fn flash_draws_snakes_red() {
+        let mut logic = new_logic();
+        logic.add_snake();
+        let mut scene = scene(&logic);
+        scene.flash = true;
+        let screen = screen(&scene);
+
+        // The head of the first snake, and the body of the second one.
+        assert_eq!(screen[3][4], ('>', Some(Color::Red)));
+        assert_eq!(screen[18][16], ('o', Some(Color::Red)));
+    }
+
+    #[test]
+    fn frames_are_logged() {
+        struct NoRenderer;
+
+        impl Renderer for NoRenderer {
+            fn draw(&mut self, _: &Scene) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut logic = new_logic();
+        let mut renderer = FileRenderer {
+            inner: NoRenderer,
+            log: Vec::new(),
+            theme: Theme::default(),
+            screen: Vec::new(),
+        };
+        renderer.draw(&scene(&logic)).unwrap();
+        logic.step();
+        renderer.draw(&scene(&logic)).unwrap();
+
+        let log = String::from_utf8(renderer.log).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 2 * 22);
+        assert!(lines[0].starts_with("--- "));
+        assert!(lines[22].starts_with("--- "));
+        assert_eq!(lines[4], format!("| xx>{}|", " ".repeat(14)));
+        assert_eq!(lines[26], format!("|  xx>{}|", " ".repeat(13)));
+    }
+
+    #[test]
+    fn head_follows_direction() {
+        let mut logic = new_logic();
+        logic.set_dir(Direction::Down);
+        logic.step();
+        let lines = lines(&scene(&logic));
+
+        assert_eq!(lines[3], format!("|  xx{}|", " ".repeat(14)));
+        assert_eq!(lines[4], format!("|   v{}|", " ".repeat(14)));
+    }
+
+    #[test]
+    fn elapsed_time_is_shown_in_header() {
+        let logic = new_logic();
+        let mut scene = scene(&logic);
+        scene.elapsed = Duration::from_secs(83);
+        assert!(lines(&scene)[0].ends_with("time: 01:23"));
+    }
+
+    #[test]
+    fn time_left_is_shown_in_header() {
+        let logic = new_logic();
+        let mut scene = scene(&logic);
+        scene.elapsed = Duration::from_secs(10);
+        scene.time_left = Some(Duration::from_millis(49_500));
+        assert!(lines(&scene)[0].ends_with("time left: 00:50"));
+    }
+
+    #[test]
+    fn paused_is_shown_in_header() {
+        let logic = new_logic();
+        let mut scene = scene(&logic);
+        scene.state = GameState::Paused;
+        assert!(lines(&scene)[0].ends_with("PAUSED"));
+    }
+
+    #[test]
+    fn diff_has_only_moved_cells() {
+        let mut logic = new_logic();
+        let prev = screen(&scene(&logic));
+        logic.step();
+        let next = screen(&scene(&logic));
+
+        // The tail left, the old head became body and the new head appeared.
+        // The header line is 0.
+        assert_eq!(
+            diff(&prev, &next).collect::<Vec<_>>(),
+            vec![(3, 2), (3, 4), (3, 5)]
+        );
+        assert_eq!(diff(&next, &next).count(), 0);
+    }
+}
@@ -0,0 +1,253 @@
+//! Recording of a game, to play it again exactly.
+
+use super::Args;
+use serde::{Deserialize, Serialize};
+use snake_game::{Coord, Direction, FeedWeights, Level, Size, SnakeGameLogic, DEFAULT_INITIAL_LEN};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Everything needed to reproduce a game: the settings of the logic, the seed and the turns.
+/// The interval of moves does not change the game, so it is not recorded.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub width: u16,
+    pub height: u16,
+    pub feeds: usize,
+    pub obstacles: usize,
+    pub wrap: bool,
+    #[serde(default)]
+    pub wrap_x: bool,
+    #[serde(default)]
+    pub wrap_y: bool,
+    pub poison: bool,
+    pub golden: bool,
+    #[serde(default)]
+    pub reverse: bool,
+    #[serde(default)]
+    pub star: bool,
+    #[serde(default)]
+    pub slow: bool,
+    #[serde(default)]
+    pub feed_weights: Option<FeedWeights>,
+    #[serde(default)]
+    pub avoid_head: bool,
+    #[serde(default)]
+    pub two_player: bool,
+    #[serde(default = "default_length")]
+    pub length: usize,
+    #[serde(default)]
+    pub start: Option<(Coord, Direction)>,
+    #[serde(default)]
+    pub feed_at: Option<Coord>,
+    #[serde(default)]
+    pub random_start: bool,
+    pub feed_ttl: Option<usize>,
+    #[serde(default)]
+    pub feed_drift: Option<usize>,
+    #[serde(default)]
+    pub bombs: Option<usize>,
+    #[serde(default)]
+    pub grace: usize,
+    #[serde(default)]
+    pub portals: usize,
+    #[serde(default = "default_lives")]
+    pub lives: usize,
+    #[serde(default = "default_growth")]
+    pub growth: usize,
+    #[serde(default)]
+    pub combo: Option<usize>,
+    #[serde(default)]
+    pub zen: bool,
+    #[serde(default)]
+    pub no_walls: bool,
+    #[serde(default)]
+    pub levels: Vec<Level>,
+    #[serde(default = "default_level_score")]
+    pub level_score: usize,
+    pub seed: u64,
+    /// Number of moves in the game.
+    pub steps: u64,
+    /// Score at the end of the game.
+    pub score: usize,
+    pub turns: Vec<Turn>,
+}
+
+/// A direction given by the player.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Turn {
+    /// Number of moves done before the direction was given.
+    pub step: u64,
+    /// Index of the player, which is 0 in a single-player game.
+    #[serde(default)]
+    pub player: usize,
+    pub dir: Direction,
+}
+
+impl Replay {
+    /// Start recording a game played with the settings.
+    pub fn new(args: &Args, seed: u64) -> Self {
+        let Size(width, height) = args.field_size;
+        Self {
+            width,
+            height,
+            feeds: args.feeds,
+            obstacles: args.obstacles,
+            wrap: args.wrap,
+            wrap_x: args.wrap_x,
+            wrap_y: args.wrap_y,
+            poison: args.poison,
+            golden: args.golden,
+            reverse: args.reverse,
+            star: args.star,
+            slow: args.slow,
+            feed_weights: args.feed_weights,
+            avoid_head: args.avoid_head,
+            two_player: args.two_player,
+            length: args.length,
+            start: args.start,
+            feed_at: args.feed_at,
+            random_start: args.random_start,
+            feed_ttl: args.feed_ttl,
+            feed_drift: args.feed_drift,
+            bombs: args.bombs,
+            grace: args.grace,
+            portals: args.portals,
+            lives: args.lives,
+            growth: args.growth,
+            combo: args.combo,
+            zen: args.zen,
+            no_walls: args.no_walls,
+            levels: args.levels.clone(),
+            level_score: args.level_score,
+            seed,
+            steps: 0,
+            score: 0,
+            turns: Vec::new(),
+        }
+    }
+
+    /// Take the settings of the recorded game.
+    pub fn apply(&self, args: &mut Args) {
+        args.field_size = Size(self.width, self.height);
+        args.feeds = self.feeds;
+        args.obstacles = self.obstacles;
+        args.wrap = self.wrap;
+        args.wrap_x = self.wrap_x;
+        args.wrap_y = self.wrap_y;
+        args.poison = self.poison;
+        args.golden = self.golden;
+        args.reverse = self.reverse;
+        args.star = self.star;
+        args.slow = self.slow;
+        args.feed_weights = self.feed_weights;
+        args.avoid_head = self.avoid_head;
+        args.two_player = self.two_player;
+        args.length = self.length;
+        args.start = self.start;
+        args.feed_at = self.feed_at;
+        args.random_start = self.random_start;
+        args.feed_ttl = self.feed_ttl;
+        args.feed_drift = self.feed_drift;
+        args.bombs = self.bombs;
+        args.grace = self.grace;
+        args.portals = self.portals;
+        args.lives = self.lives;
+        args.growth = self.growth;
+        args.combo = self.combo;
+        args.zen = self.zen;
+        args.no_walls = self.no_walls;
+        args.levels = self.levels.clone();
+        args.level_score = self.level_score;
+        args.seed = Some(self.seed);
+        // The replay ends where the recording did.
+        args.time_limit = None;
+    }
+
+    /// Players and the directions they gave after `step` moves.
+    pub fn turns_at(&self, step: u64) -> impl Iterator<Item = (usize, Direction)> + '_ {
+        self.turns
+            .iter()
+            .filter(move |turn| turn.step == step)
+            .map(|turn| (turn.player, turn.dir))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read `{}`: {}", path.display(), e))?;
+        toml::from_str(&text).map_err(|e| format!("invalid replay `{}`: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let text = toml::to_string(self).map_err(io::Error::other)?;
+        fs::write(path, text).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("failed to write `{}`: {}", path.display(), e),
+            )
+        })
+    }
+}
+
+/// A recorded game played along with the live one, to race against it.
+/// Only the head of the first snake is shown, and it does not touch the live game.
+pub struct Ghost {
+    replay: Replay,
+    logic: SnakeGameLogic,
+    /// Number of moves done.
+    steps: u64,
+    /// The recorded game has ended.
+    done: bool,
+}
+
+impl Ghost {
+    /// Play the recording on the logic set up with its settings.
+    pub fn new(replay: Replay, logic: SnakeGameLogic) -> Self {
+        Self {
+            replay,
+            logic,
+            steps: 0,
+            done: false,
+        }
+    }
+
+    /// Move by one step, like the recorded game did.
+    pub fn step(&mut self) {
+        if self.done {
+            return;
+        }
+        if self.steps == self.replay.steps {
+            self.done = true;
+            return;
+        }
+        for (player, d) in self.replay.turns_at(self.steps) {
+            self.logic.set_dir_of(player, d);
+        }
+        self.steps += 1;
+        self.done = self.logic.step().is_over();
+    }
+
+    /// Cell of the head, until the recorded game ends.
+    pub fn head(&self) -> Option<Coord> {
+        (!self.done).then(|| *self.logic.body().next().unwrap())
+    }
+}
+
+/// Recordings made before the length was configurable used the default.
+fn default_length() -> usize {
+    DEFAULT_INITIAL_LEN
+}
+
+/// Recordings made before lives were added had only one.
+fn default_lives() -> usize {
+    1
+}
+
+/// Recordings made before the growth was configurable grew by one segment.
+fn default_growth() -> usize {
+    1
+}
+
+fn default_level_score() -> usize {
+    10
+}
@@ -0,0 +1,127 @@
+//! A game in progress, saved when the player quits to resume it later.
+
+use serde::{Deserialize, Serialize};
+use snake_game::SnakeGameLogic;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// A save made by another version of the game is not loaded.
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The state of the game and of the controler around it.
+/// `L` is a reference to the logic when saving.
+#[derive(Serialize, Deserialize)]
+pub struct SavedGame<L = SnakeGameLogic> {
+    version: String,
+    pub logic: L,
+    /// Time played before the game was saved.
+    pub elapsed: Duration,
+    /// Index of the level being played.
+    pub level: usize,
+    /// Feeds eaten before the game was saved.
+    pub eaten: u64,
+}
+
+impl<'a> SavedGame<&'a SnakeGameLogic> {
+    pub fn new(logic: &'a SnakeGameLogic, elapsed: Duration, level: usize, eaten: u64) -> Self {
+        Self {
+            version: VERSION.to_string(),
+            logic,
+            elapsed,
+            level,
+            eaten,
+        }
+    }
+}
+
+/// The file `~/.snake_save.json`, which holds at most one game.
+#[derive(Default)]
+pub struct SaveFile {
+    /// Nothing is saved if None.
+    path: Option<PathBuf>,
+}
+
+impl SaveFile {
+    pub fn new() -> Self {
+        let path = env::var_os("HOME").map(|home| PathBuf::from(home).join(".snake_save.json"));
+        Self { path }
+    }
+
+    pub fn save(&self, game: &SavedGame<&SnakeGameLogic>) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let text = serde_json::to_string(game)?;
+        fs::write(path, text + "\n")
+    }
+
+    /// Load the saved game and remove it, so that it is resumed only once.
+    /// A missing file is `Ok(None)`, and a broken one or one of another version is an error.
+    pub fn take(&self) -> Result<Option<SavedGame>, String> {
+        let Some(path) = &self.path else {
+            return Ok(None);
+        };
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("failed to read `{}`: {}", path.display(), e)),
+        };
+        let _ = fs::remove_file(path);
+        parse(&text).map(Some)
+    }
+}
+
+fn parse(text: &str) -> Result<SavedGame, String> {
+    #[derive(Deserialize)]
+    struct Version {
+        version: String,
+    }
+    let version = serde_json::from_str::<Version>(text)
+        .map_err(|e| format!("the saved game is broken: {}", e))?
+        .version;
+    if version != VERSION {
+        return Err(format!(
+            "the game was saved by version {}, not {}",
+            version, VERSION
+        ));
+    }
+    serde_json::from_str(text).map_err(|e| format!("the saved game is broken: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snake_game::{Direction, Size};
+
+    #[test]
+    fn save_and_load() {
+        let mut logic = SnakeGameLogic::new(Size(10, 10), 2, 0);
+        logic.set_dir(Direction::Down);
+        logic.step();
+        let game = SavedGame::new(&logic, Duration::from_secs(3), 1, 4);
+        let text = serde_json::to_string(&game).unwrap();
+
+        let loaded = parse(&text).unwrap();
+        assert_eq!(loaded.elapsed, Duration::from_secs(3));
+        assert_eq!((loaded.level, loaded.eaten), (1, 4));
+        let mut logic = loaded.logic;
+        assert!(logic.body().eq(game.logic.body()));
+        assert_eq!(logic.feeds(), game.logic.feeds());
+        assert_eq!(*logic.get_dir(), Direction::Down);
+        // The game goes on the same way every time it is loaded.
+        let mut again = parse(&text).unwrap().logic;
+        for _ in 0..5 {
+            assert_eq!(logic.step(), again.step());
+        }
+        assert_eq!(logic.feeds(), again.feeds());
+    }
+
+    #[test]
+    fn other_version_is_error() {
+        assert!(parse("{").is_err());
+        assert!(parse(r#"{"version": "0.0.0"}"#).is_err());
+    }
+}
@@ -0,0 +1,87 @@
+//! Statistics of every game played, kept across sessions.
+
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Totals stored in `~/.snake_stats.json`.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    /// Where to save. Nothing is saved if None.
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    pub games_played: u64,
+    /// Feeds and golden feeds eaten.
+    pub food_eaten: u64,
+    pub best_score: usize,
+    /// Time spent playing in seconds.
+    pub time_played: u64,
+}
+
+impl Stats {
+    /// Load the statistics.
+    /// A missing or broken file is treated as no games played.
+    pub fn load() -> Self {
+        let path = env::var_os("HOME").map(|home| PathBuf::from(home).join(".snake_stats.json"));
+        let stats: Self = path
+            .as_ref()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { path, ..stats }
+    }
+
+    /// Add a game which ended, and save the statistics.
+    pub fn record(&mut self, score: usize, food_eaten: u64, time: Duration) {
+        self.games_played += 1;
+        self.food_eaten += food_eaten;
+        self.best_score = self.best_score.max(score);
+        self.time_played += time.as_secs();
+        if let (Some(path), Ok(text)) = (&self.path, serde_json::to_string_pretty(self)) {
+            // Failing to save must not end the game.
+            let _ = fs::write(path, text + "\n");
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let secs = self.time_played;
+        writeln!(f, "games played: {}", self.games_played)?;
+        writeln!(f, "food eaten:   {}", self.food_eaten)?;
+        writeln!(f, "best score:   {}", self.best_score)?;
+        write!(
+            f,
+            "time played:  {}:{:02}:{:02}",
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_game() {
+        let mut stats = Stats::default();
+        stats.record(12, 10, Duration::from_secs(90));
+        stats.record(5, 5, Duration::from_secs(3600));
+        assert_eq!(stats.games_played, 2);
+        assert_eq!(stats.food_eaten, 15);
+        assert_eq!(stats.best_score, 12);
+        assert_eq!(
+            stats.to_string().lines().last(),
+            Some("time played:  1:01:30")
+        );
+
+        let text = serde_json::to_string(&stats).unwrap();
+        assert_eq!(serde_json::from_str::<Stats>(&text).unwrap(), stats);
+    }
+}